@@ -8,7 +8,8 @@ use sunburst::{
     canvas::{Canvas, Color, IntPoint},
     path::Path,
     renderer::RendererType::SDL2,
-    sketch::{Sketch, SketchMetrics},
+    sketch::{DrawContext, Sketch, SketchMetrics},
+    Error,
 };
 
 const SPINNER_RADIUS: usize = 25;
@@ -49,9 +50,9 @@ impl LissaJousFigure {
     fn add_point(&mut self, col_spinner: &LissaJousSpinner, row_spinner: &LissaJousSpinner) {
         let next_point = IntPoint::new(col_spinner.indicator_pos.x, row_spinner.indicator_pos.y);
         if self.points.segment_count() == 0 {
-            self.points.move_to(&next_point);
+            self.points.move_to(next_point);
         } else {
-            self.points.line_to(&next_point);
+            self.points.line_to(next_point);
         }
     }
 
@@ -82,20 +83,20 @@ impl LissaJousSpinner {
     fn show(&self, canvas: &mut Canvas) {
         canvas.stroke(Color::rgb(0, 0, 0));
         canvas.no_fill();
-        canvas.draw_ellipse(&self.center, self.radius, self.radius);
+        canvas.draw_ellipse(self.center, self.radius as f32, self.radius as f32);
 
         canvas.stroke(Color::rgb(200, 200, 200));
         match self.ty {
             SpinnerType::Column => {
                 canvas.draw_line(
-                    &IntPoint::new(self.indicator_pos.x, 0),
-                    &IntPoint::new(self.indicator_pos.x, canvas.height() as isize),
+                    IntPoint::new(self.indicator_pos.x, 0),
+                    IntPoint::new(self.indicator_pos.x, canvas.height() as isize),
                 );
             }
             SpinnerType::Row => {
                 canvas.draw_line(
-                    &IntPoint::new(0, self.indicator_pos.y),
-                    &IntPoint::new(canvas.width() as isize, self.indicator_pos.y),
+                    IntPoint::new(0, self.indicator_pos.y),
+                    IntPoint::new(canvas.width() as isize, self.indicator_pos.y),
                 );
             }
         }
@@ -106,7 +107,7 @@ impl LissaJousSpinner {
             self.indicator_pos.x - INDICATOR_SIZE / 2,
             self.indicator_pos.y - INDICATOR_SIZE / 2,
         );
-        canvas.draw_square(&indicator_pos, INDICATOR_SIZE);
+        canvas.draw_square(indicator_pos, INDICATOR_SIZE as f32);
     }
 
     fn update(&mut self) {
@@ -162,7 +163,9 @@ fn state_create() -> SketchState {
     }
 }
 
-fn setup(_: &mut Sketch<SketchState>) {}
+fn setup(sketch: &mut Sketch<SketchState>) {
+    sketch.canvas_mut().smooth();
+}
 
 fn update(state: &mut SketchState, _: &SketchMetrics) {
     for column_spinner in state.column_spinners.iter_mut() {
@@ -195,7 +198,8 @@ fn update(state: &mut SketchState, _: &SketchMetrics) {
     }
 }
 
-fn draw(canvas: &mut Canvas, state: &SketchState, _: &SketchMetrics) {
+fn draw(ctx: DrawContext<SketchState>) {
+    let DrawContext { canvas, state, .. } = ctx;
     canvas.clear();
 
     for column_spinner in &state.column_spinners {
@@ -211,12 +215,12 @@ fn draw(canvas: &mut Canvas, state: &SketchState, _: &SketchMetrics) {
     }
 }
 
-fn main() {
+fn main() -> Result<(), Error> {
     let sketch = Sketch::new(state_create, 900, 900)
-        .renderer(SDL2("Lissajous"))
+        .try_renderer(SDL2("Lissajous"))?
         .setup(setup)
         .update(update)
         .draw(draw);
 
-    sketch.run();
+    sketch.run()
 }
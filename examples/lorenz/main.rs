@@ -1,13 +1,14 @@
 extern crate sunburst;
 
 use sunburst::{
-    canvas::{Canvas, IntPoint},
+    canvas::FloatPoint,
     path::Path,
     renderer::RendererType::SDL2,
-    sketch::{Sketch, SketchMetrics},
+    sketch::{DrawContext, Sketch, SketchMetrics},
+    Error,
 };
 
-const ORIGIN: isize = 450;
+const ORIGIN: f32 = 450.0;
 const A: f32 = 10.0;
 const B: f32 = 28.0;
 const C: f32 = 8.0 / 3.0;
@@ -22,7 +23,7 @@ struct SketchState {
 
 fn state_create() -> SketchState {
     let mut path = Path::new();
-    path.move_to(&IntPoint::new(ORIGIN, ORIGIN));
+    path.move_to(FloatPoint::new(ORIGIN, ORIGIN));
     SketchState {
         x: 0.5,
         y: 0.05,
@@ -39,21 +40,21 @@ fn update(state: &mut SketchState, _: &SketchMetrics) {
     state.y += dy;
     state.z += dz;
 
-    state.path.line_to(&IntPoint::new(
-        ORIGIN + (14.0 * state.x) as isize,
-        ORIGIN + (14.0 * state.y) as isize,
+    state.path.line_to(FloatPoint::new(
+        ORIGIN + 14.0 * state.x,
+        ORIGIN + 14.0 * state.y,
     ));
 }
 
-fn draw(canvas: &mut Canvas, state: &SketchState, _: &SketchMetrics) {
-    canvas.clear();
-    canvas.draw_path(&state.path);
+fn draw(ctx: DrawContext<SketchState>) {
+    ctx.canvas.clear();
+    ctx.canvas.draw_path(&ctx.state.path);
 }
 
-fn main() {
+fn main() -> Result<(), Error> {
     let sketch = Sketch::new(state_create, 900, 900)
-        .renderer(SDL2("Lorenz Attractor"))
+        .try_renderer(SDL2("Lorenz Attractor"))?
         .update(update)
         .draw(draw);
-    sketch.run();
+    sketch.run()
 }
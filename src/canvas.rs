@@ -1,40 +1,75 @@
-use crate::path::PathSegment;
+use crate::display_list::{DisplayList, Shape, ShapeStyle, TextRun};
+use crate::font::FontFace;
+use crate::image::tinted;
 use crate::pixelbuffer::PixelBuffer;
+use crate::raster;
+use crate::stroke::{self, StrokeStyle};
 
-pub use super::color::Color;
-pub use super::path::Path;
-pub use super::primitives::{IntPoint, IntRect};
+pub use super::color::{BlendMode, Color, ColorMode};
+pub use super::display_list::Recording;
+pub use super::font::{Font, FontWeight, TextAlign, VerticalAlign};
+pub use super::image::{Image, ImageSampling};
+pub use super::path::{FillRule, Path};
+pub use super::primitives::{FloatPoint, FloatRect, IntPoint, IntRect};
+pub use super::stroke::{LineCap, LineJoin};
+pub use super::transform::Transform;
 
-use noto_sans_mono_bitmap::{get_bitmap, get_bitmap_width, BitmapHeight};
-use std::cmp::max;
-use std::cmp::min;
 use std::mem::swap;
+use std::sync::Arc;
 
-pub enum FontWeight {
-    Light,
-    Regular,
-    Bold,
-}
-
+#[derive(Clone)]
 pub struct TextStyle {
     weight: FontWeight,
     size: usize,
+    font: Option<Font>,
+    align: TextAlign,
+    vertical_align: VerticalAlign,
+    line_spacing: f32,
 }
 
 impl TextStyle {
     pub fn new(size: usize, weight: FontWeight) -> Self {
-        TextStyle { size, weight }
+        TextStyle {
+            size,
+            weight,
+            font: None,
+            align: TextAlign::Left,
+            vertical_align: VerticalAlign::Top,
+            line_spacing: 1.0,
+        }
+    }
+
+    fn font_face(&self) -> FontFace<'_> {
+        FontFace::new(self.font.as_ref(), self.size, self.weight)
     }
 }
 
-pub struct Canvas {
-    pixelbuffer: PixelBuffer,
+/// Everything `push` saves and `pop` restores.
+#[derive(Clone)]
+struct DrawState {
     fill: Option<Color>,
     stroke: Option<Color>,
-    background: Color,
+    stroke_style: StrokeStyle,
     text_style: TextStyle,
+    smooth: bool,
+    blend_mode: BlendMode,
+    fill_rule: FillRule,
+    curve_tolerance: f32,
+    tint: Option<Color>,
+    image_sampling: ImageSampling,
+    color_mode: ColorMode,
+    matrix: Transform,
 }
 
+pub struct Canvas {
+    pixelbuffer: PixelBuffer,
+    background: Color,
+    state: DrawState,
+    state_stack: Vec<DrawState>,
+    recording: Option<(Recording, DisplayList)>,
+}
+
+#[derive(Clone, Copy)]
 enum ColorSource {
     Fill,
     Stroke,
@@ -44,10 +79,27 @@ impl Canvas {
     pub fn new(width: usize, height: usize) -> Self {
         Canvas {
             pixelbuffer: PixelBuffer::new(width, height),
-            stroke: Some(Color::rgb(0, 0, 0)),
-            fill: None,
             background: Color::rgb(255, 255, 255),
-            text_style: TextStyle::new(16, FontWeight::Regular),
+            state: DrawState {
+                fill: None,
+                stroke: Some(Color::rgb(0, 0, 0)),
+                stroke_style: StrokeStyle {
+                    weight: 1.0,
+                    cap: LineCap::Round,
+                    join: LineJoin::Miter,
+                },
+                text_style: TextStyle::new(16, FontWeight::Regular),
+                smooth: false,
+                blend_mode: BlendMode::SourceOver,
+                fill_rule: FillRule::NonZero,
+                curve_tolerance: 0.25,
+                tint: None,
+                image_sampling: ImageSampling::Nearest,
+                color_mode: ColorMode::default(),
+                matrix: Transform::identity(),
+            },
+            state_stack: Vec::new(),
+            recording: None,
         }
     }
 
@@ -72,74 +124,493 @@ impl Canvas {
     }
 
     pub fn stroke(&mut self, color: Color) {
-        self.stroke = Some(color);
+        self.state.stroke = Some(color);
     }
 
     pub fn fill(&mut self, color: Color) {
-        self.fill = Some(color);
+        self.state.fill = Some(color);
     }
 
     pub fn no_fill(&mut self) {
-        self.fill = None;
+        self.state.fill = None;
     }
 
     pub fn no_stroke(&mut self) {
-        self.stroke = None;
+        self.state.stroke = None;
+    }
+
+    /// Sets the color space and ranges `color` and `color_alpha` interpret
+    /// their arguments in.
+    pub fn color_mode(&mut self, mode: ColorMode) {
+        self.state.color_mode = mode;
+    }
+
+    pub fn color(&self, v1: f32, v2: f32, v3: f32) -> Color {
+        let alpha = self.state.color_mode.ranges()[3];
+        self.color_alpha(v1, v2, v3, alpha)
+    }
+
+    pub fn color_alpha(&self, v1: f32, v2: f32, v3: f32, alpha: f32) -> Color {
+        self.state.color_mode.color(v1, v2, v3, alpha)
+    }
+
+    pub fn fill_rule(&mut self, rule: FillRule) {
+        self.state.fill_rule = rule;
+    }
+
+    /// Maximum distance in pixels between a curve and the line segments it
+    /// is drawn with.
+    pub fn curve_tolerance(&mut self, tolerance: f32) {
+        self.state.curve_tolerance = tolerance;
+    }
+
+    pub fn stroke_weight(&mut self, weight: f32) {
+        self.state.stroke_style.weight = weight.max(0.0);
+    }
+
+    pub fn stroke_cap(&mut self, cap: LineCap) {
+        self.state.stroke_style.cap = cap;
+    }
+
+    pub fn stroke_join(&mut self, join: LineJoin) {
+        self.state.stroke_style.join = join;
+    }
+
+    /// Strokes wider than a pixel after transformation are drawn as polygons.
+    fn is_thick_stroke(&self) -> bool {
+        self.state.stroke_style.weight * self.state.matrix.scale_factor() > 1.0
     }
 
     pub fn font_size(&mut self, size: usize) {
-        self.text_style.size = size;
+        self.state.text_style.size = size;
     }
 
+    /// Only affects the bundled bitmap font, loaded fonts come with their own weight.
     pub fn font_weight(&mut self, weight: FontWeight) {
-        self.text_style.weight = weight;
+        self.state.text_style.weight = weight;
+    }
+
+    /// Renders text with `font` at the pixel size set by `font_size`.
+    pub fn text_font(&mut self, font: &Font) {
+        self.state.text_style.font = Some(font.clone());
+    }
+
+    /// Switches back to the bundled bitmap font.
+    pub fn default_text_font(&mut self) {
+        self.state.text_style.font = None;
+    }
+
+    pub fn text_align(&mut self, horizontal: TextAlign, vertical: VerticalAlign) {
+        self.state.text_style.align = horizontal;
+        self.state.text_style.vertical_align = vertical;
+    }
+
+    /// Distance between lines as a multiple of the font's line height.
+    pub fn line_spacing(&mut self, spacing: f32) {
+        self.state.text_style.line_spacing = spacing;
+    }
+
+    /// Width of the widest line of `msg` with the current font.
+    pub fn text_width(&self, msg: &str) -> f32 {
+        let face = self.state.text_style.font_face();
+        msg.split('\n')
+            .map(|line| face.line_width(line))
+            .fold(0.0, f32::max)
+    }
+
+    /// Area covered by `msg` if drawn at the origin with the current alignment.
+    pub fn text_bounds(&self, msg: &str) -> IntRect {
+        let face = self.state.text_style.font_face();
+        let lines: Vec<&str> = msg.split('\n').collect();
+        let positions = self.layout_text_lines(&face, &lines, None);
+        let line_height = face.line_height();
+
+        let mut left = f32::MAX;
+        let mut right = f32::MIN;
+        for (line, position) in lines.iter().zip(&positions) {
+            left = left.min(position.x);
+            right = right.max(position.x + face.line_width(line));
+        }
+        let top = positions[0].y;
+        let bottom = positions.last().unwrap().y + line_height;
+
+        let location = IntPoint::new(left.floor() as isize, top.floor() as isize);
+        IntRect::new(
+            location,
+            right.ceil() as isize - location.x,
+            bottom.ceil() as isize - location.y,
+        )
+    }
+
+    /// Top left corner of every line relative to the anchor point, or to the
+    /// corner of the box of the given size the lines are laid out in.
+    fn layout_text_lines(
+        &self,
+        face: &FontFace,
+        lines: &[&str],
+        box_size: Option<(f32, f32)>,
+    ) -> Vec<FloatPoint> {
+        let style = &self.state.text_style;
+        let line_height = face.line_height();
+        let line_advance = line_height * style.line_spacing;
+        let block_height = line_advance * (lines.len() as f32 - 1.0) + line_height;
+        let (box_width, box_height) = box_size.unwrap_or((0.0, 0.0));
+
+        let top = match style.vertical_align {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Baseline if box_size.is_some() => 0.0,
+            VerticalAlign::Baseline => -face.ascent(),
+            VerticalAlign::Middle => (box_height - block_height) / 2.0,
+            VerticalAlign::Bottom => box_height - block_height,
+        };
+
+        lines
+            .iter()
+            .enumerate()
+            .map(|(line_i, line)| {
+                let width = face.line_width(line);
+                let x = match style.align {
+                    TextAlign::Left => 0.0,
+                    TextAlign::Center => (box_width - width) / 2.0,
+                    TextAlign::Right => box_width - width,
+                };
+                FloatPoint::new(x, top + line_i as f32 * line_advance)
+            })
+            .collect()
+    }
+
+    /// Draws lines, paths and ellipse outlines anti-aliased.
+    pub fn smooth(&mut self) {
+        self.state.smooth = true;
+    }
+
+    pub fn no_smooth(&mut self) {
+        self.state.smooth = false;
+    }
+
+    pub fn is_smooth(&self) -> bool {
+        self.state.smooth
+    }
+
+    pub fn blend_mode(&mut self, mode: BlendMode) {
+        self.state.blend_mode = mode;
+    }
+
+    /// Multiplies the color of drawn images with `color`, a translucent tint
+    /// fades the image.
+    pub fn tint(&mut self, color: Color) {
+        self.state.tint = Some(color);
+    }
+
+    pub fn no_tint(&mut self) {
+        self.state.tint = None;
+    }
+
+    pub fn image_sampling(&mut self, sampling: ImageSampling) {
+        self.state.image_sampling = sampling;
+    }
+
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.apply_matrix(&Transform::translation(x, y));
+    }
+
+    /// Rotates by `angle` radians, clockwise on screen.
+    pub fn rotate(&mut self, angle: f32) {
+        self.apply_matrix(&Transform::rotation(angle));
+    }
+
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.apply_matrix(&Transform::scaling(x, y));
+    }
+
+    /// Shears by the given angles in radians along the x and y axis.
+    pub fn shear(&mut self, angle_x: f32, angle_y: f32) {
+        self.apply_matrix(&Transform::shearing(angle_x, angle_y));
+    }
+
+    pub fn apply_matrix(&mut self, transform: &Transform) {
+        self.state.matrix = self.state.matrix.multiply(transform);
+    }
+
+    pub fn reset_matrix(&mut self) {
+        self.state.matrix = Transform::identity();
+    }
+
+    pub fn matrix(&self) -> Transform {
+        self.state.matrix
+    }
+
+    /// Saves the transform along with fill, stroke, text style and the other
+    /// drawing settings until the matching `pop`.
+    pub fn push(&mut self) {
+        self.state_stack.push(self.state.clone());
+    }
+
+    /// Restores the state saved by the last `push`, does nothing if there is none.
+    pub fn pop(&mut self) {
+        if let Some(state) = self.state_stack.pop() {
+            self.state = state;
+        }
     }
 
     pub fn clear(&mut self) {
+        if let Some((mode, ref mut display_list)) = self.recording {
+            display_list.clear(self.background);
+            if mode == Recording::VectorOnly {
+                return;
+            }
+        }
         self.pixelbuffer.clear(&self.background);
     }
 
+    /// Starts recording the shapes drawn from now on into an empty display
+    /// list, e.g. to export the frame as SVG.
+    pub fn begin_recording(&mut self, mode: Recording) {
+        self.recording = Some((mode, DisplayList::new(self.width(), self.height())));
+    }
+
+    /// Stops recording and returns everything recorded since the last `clear`.
+    pub fn end_recording(&mut self) -> Option<DisplayList> {
+        self.recording.take().map(|(_, display_list)| display_list)
+    }
+
+    pub fn display_list(&self) -> Option<&DisplayList> {
+        self.recording
+            .as_ref()
+            .map(|(_, display_list)| display_list)
+    }
+
+    /// Draws a display list recorded on any canvas, scaled to the size of this
+    /// one. Smoothing follows this canvas, its drawing state is left untouched.
+    pub fn replay(&mut self, display_list: &DisplayList) {
+        let scale = Transform::scaling(
+            self.width() as f32 / display_list.width().max(1) as f32,
+            self.height() as f32 / display_list.height().max(1) as f32,
+        );
+        let background = self.background;
+        self.push();
+
+        if let Some(color) = display_list.background() {
+            self.background = color;
+            self.clear();
+        }
+        for recorded in display_list.shapes() {
+            let style = &recorded.style;
+            self.state.fill = style.fill;
+            self.state.stroke = style.stroke;
+            self.state.stroke_style = StrokeStyle {
+                weight: style.stroke_weight,
+                cap: style.stroke_cap,
+                join: style.stroke_join,
+            };
+            self.state.fill_rule = style.fill_rule;
+            self.state.blend_mode = style.blend_mode;
+            self.state.curve_tolerance = style.curve_tolerance;
+            self.state.matrix = scale.multiply(&style.matrix);
+
+            match &recorded.shape {
+                Shape::Point(pt) => self.draw_point(*pt),
+                Shape::Line { start, end } => self.draw_line(*start, *end),
+                Shape::Rect(rect) => self.draw_rect(*rect),
+                Shape::Ellipse {
+                    center,
+                    radius_x,
+                    radius_y,
+                } => self.draw_ellipse(*center, *radius_x, *radius_y),
+                Shape::Path(path) => self.draw_path(path),
+                Shape::Text {
+                    runs,
+                    font,
+                    size,
+                    weight,
+                } => {
+                    let mut text_style = TextStyle::new(*size, *weight);
+                    text_style.font = font.clone();
+                    text_style.vertical_align = VerticalAlign::Baseline;
+                    self.state.text_style = text_style;
+                    for run in runs {
+                        self.draw_text_lines(run.position, &[&run.text], None);
+                    }
+                }
+                Shape::Image {
+                    image,
+                    dest,
+                    sampling,
+                } => {
+                    self.state.tint = None;
+                    self.state.image_sampling = *sampling;
+                    self.draw_image(image, *dest, None);
+                }
+            }
+        }
+
+        self.pop();
+        self.background = background;
+    }
+
+    /// Adds the shape to the display list if one is recorded, returns `true`
+    /// if it must not be rasterized.
+    fn record(&mut self, shape: impl FnOnce() -> Shape) -> bool {
+        let (mode, display_list) = match self.recording {
+            Some((mode, ref mut display_list)) => (mode, display_list),
+            None => return false,
+        };
+        let style = ShapeStyle {
+            fill: self.state.fill,
+            stroke: self.state.stroke,
+            stroke_weight: self.state.stroke_style.weight,
+            stroke_cap: self.state.stroke_style.cap,
+            stroke_join: self.state.stroke_style.join,
+            fill_rule: self.state.fill_rule,
+            blend_mode: self.state.blend_mode,
+            curve_tolerance: self.state.curve_tolerance,
+            matrix: self.state.matrix,
+        };
+        display_list.push(shape(), style);
+        mode == Recording::VectorOnly
+    }
+
     fn set_pixel_from_color_source(&mut self, x: isize, y: isize, color_source: ColorSource) {
+        self.blend_pixel_from_color_source(x, y, color_source, 1.0);
+    }
+
+    fn blend_pixel_from_color_source(
+        &mut self,
+        x: isize,
+        y: isize,
+        color_source: ColorSource,
+        coverage: f32,
+    ) {
         let color = match color_source {
-            ColorSource::Fill if self.fill.is_some() => self.fill.as_ref().unwrap(),
-            ColorSource::Stroke if self.stroke.is_some() => self.stroke.as_ref().unwrap(),
+            ColorSource::Fill if self.state.fill.is_some() => self.state.fill.as_ref().unwrap(),
+            ColorSource::Stroke if self.state.stroke.is_some() => {
+                self.state.stroke.as_ref().unwrap()
+            }
             _ => {
                 return;
             }
         };
-        self.pixelbuffer.set_pixel(x, y, color);
+        self.pixelbuffer
+            .blend_pixel(x, y, color, coverage, self.state.blend_mode);
     }
 
-    pub fn draw_point(&mut self, point: &IntPoint) {
-        self.set_pixel_from_color_source(point.x, point.y, ColorSource::Stroke);
+    fn fill_contours_from_color_source(
+        &mut self,
+        contours: &[Vec<(f32, f32)>],
+        rule: FillRule,
+        color_source: ColorSource,
+    ) {
+        let (width, height, anti_alias) = (self.width(), self.height(), self.state.smooth);
+        raster::fill_contours(
+            contours,
+            rule,
+            width,
+            height,
+            anti_alias,
+            |x, y, coverage| {
+                self.blend_pixel_from_color_source(x, y, color_source, coverage);
+            },
+        );
     }
 
-    /// https://www.geeksforgeeks.org/bresenhams-line-generation-algorithm/
-    pub fn draw_line(&mut self, start: &IntPoint, end: &IntPoint) {
-        // vertical line
-        if start.x == end.x {
-            let min_y = min(start.y, end.y);
-            let max_y = max(start.y, end.y);
-            //TODO: step by stroke weight
-            for y in (min_y..max_y).step_by(1) {
-                self.set_pixel_from_color_source(start.x, y, ColorSource::Stroke);
+    fn stroke_polylines(&mut self, polylines: &[(Vec<(f32, f32)>, bool)]) {
+        if self.state.stroke.is_none() {
+            return;
+        }
+        let contours: Vec<Vec<(f32, f32)>> = polylines
+            .iter()
+            .flat_map(|(points, closed)| {
+                stroke::stroke_polyline(points, *closed, &self.state.stroke_style)
+            })
+            .collect();
+        let contours = self.transform_contours(contours);
+        self.fill_contours_from_color_source(&contours, FillRule::NonZero, ColorSource::Stroke);
+    }
+
+    fn transform_contours(&self, mut contours: Vec<Vec<(f32, f32)>>) -> Vec<Vec<(f32, f32)>> {
+        if !self.state.matrix.is_identity() {
+            for point in contours.iter_mut().flatten() {
+                *point = self.state.matrix.apply_tuple(*point);
             }
         }
+        contours
+    }
 
-        // horizontal line
-        if start.y == end.y {
-            let min_x = min(start.x, end.x);
-            let max_x = max(start.x, end.x);
-            //TODO: step by stroke weight
-            for x in (min_x..max_x).step_by(1) {
-                self.set_pixel_from_color_source(x, start.y, ColorSource::Stroke);
+    /// Fills and strokes polylines given in user space, used for every shape
+    /// that cannot be drawn with one of the pixel exact algorithms.
+    fn draw_polylines(&mut self, polylines: &[(Vec<(f32, f32)>, bool)]) {
+        if self.state.fill.is_some() {
+            let contours: Vec<Vec<(f32, f32)>> = polylines
+                .iter()
+                .filter(|(points, closed)| *closed && points.len() > 2)
+                .map(|(points, _)| points.clone())
+                .collect();
+            let contours = self.transform_contours(contours);
+            self.fill_contours_from_color_source(
+                &contours,
+                self.state.fill_rule,
+                ColorSource::Fill,
+            );
+        }
+
+        if self.state.stroke.is_some() && self.is_thick_stroke() {
+            self.stroke_polylines(polylines);
+        } else if self.state.stroke.is_some() {
+            let matrix = self.state.matrix;
+            for (points, closed) in polylines {
+                let points: Vec<FloatPoint> = points
+                    .iter()
+                    .map(|point| matrix.apply(&FloatPoint::from(*point)))
+                    .collect();
+                for pair in points.windows(2) {
+                    self.draw_hairline(pair[0], pair[1]);
+                }
+                if *closed && points.len() > 2 {
+                    self.draw_hairline(*points.last().unwrap(), points[0]);
+                }
             }
         }
+    }
+
+    pub fn draw_point(&mut self, point: impl Into<FloatPoint>) {
+        let point = point.into();
+        if self.record(|| Shape::Point(point)) {
+            return;
+        }
+        let point = self.state.matrix.apply(&point).round();
+        self.set_pixel_from_color_source(point.x, point.y, ColorSource::Stroke);
+    }
+
+    pub fn draw_line(&mut self, start: impl Into<FloatPoint>, end: impl Into<FloatPoint>) {
+        let (start, end) = (start.into(), end.into());
+        if self.record(|| Shape::Line { start, end }) {
+            return;
+        }
+        self.stroke_line(start, end);
+    }
 
-        // TODO: avoid this clone
-        let mut point1 = start.clone();
-        let mut point2 = end.clone();
+    /// Rasterizes a line given in user space with the current stroke.
+    fn stroke_line(&mut self, start: FloatPoint, end: FloatPoint) {
+        if self.is_thick_stroke() {
+            let points = vec![(start.x, start.y), (end.x, end.y)];
+            self.stroke_polylines(&[(points, false)]);
+        } else {
+            let matrix = self.state.matrix;
+            self.draw_hairline(matrix.apply(&start), matrix.apply(&end));
+        }
+    }
 
+    /// One pixel wide line between points that are already transformed.
+    fn draw_hairline(&mut self, start: FloatPoint, end: FloatPoint) {
+        if self.state.smooth {
+            self.draw_line_smooth(start.x, start.y, end.x, end.y);
+        } else {
+            self.draw_line_aliased(start.round(), end.round());
+        }
+    }
+
+    /// https://www.geeksforgeeks.org/bresenhams-line-generation-algorithm/
+    fn draw_line_aliased(&mut self, mut point1: IntPoint, mut point2: IntPoint) {
         let adx = (point2.x - point1.x).abs();
         let ady = (point2.y - point1.y).abs();
         if adx > ady {
@@ -183,8 +654,81 @@ impl Canvas {
         }
     }
 
-    pub fn draw_rect(&mut self, rect: &IntRect) {
-        if self.fill.is_some() {
+    /// https://en.wikipedia.org/wiki/Xiaolin_Wu%27s_line_algorithm
+    fn draw_line_smooth(&mut self, mut x0: f32, mut y0: f32, mut x1: f32, mut y1: f32) {
+        let fpart = |v: f32| v - v.floor();
+        let rfpart = |v: f32| 1.0 - fpart(v);
+
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep {
+            swap(&mut x0, &mut y0);
+            swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            swap(&mut x0, &mut x1);
+            swap(&mut y0, &mut y1);
+        }
+
+        let plot = |canvas: &mut Canvas, x: f32, y: f32, coverage: f32| {
+            let (x, y) = if steep { (y, x) } else { (x, y) };
+            canvas.blend_pixel_from_color_source(
+                x as isize,
+                y as isize,
+                ColorSource::Stroke,
+                coverage,
+            );
+        };
+
+        let dx = x1 - x0;
+        let dy = y1 - y0;
+        let gradient = if dx == 0.0 { 1.0 } else { dy / dx };
+
+        let x_end = x0.round();
+        let y_end = y0 + gradient * (x_end - x0);
+        let x_gap = rfpart(x0 + 0.5);
+        let x_start = x_end;
+        plot(self, x_start, y_end.floor(), rfpart(y_end) * x_gap);
+        plot(self, x_start, y_end.floor() + 1.0, fpart(y_end) * x_gap);
+        let mut inter_y = y_end + gradient;
+
+        let x_end = x1.round();
+        let y_end = y1 + gradient * (x_end - x1);
+        let x_gap = fpart(x1 + 0.5);
+        let x_stop = x_end;
+        plot(self, x_stop, y_end.floor(), rfpart(y_end) * x_gap);
+        plot(self, x_stop, y_end.floor() + 1.0, fpart(y_end) * x_gap);
+
+        for x in (x_start as isize + 1)..(x_stop as isize) {
+            plot(self, x as f32, inter_y.floor(), rfpart(inter_y));
+            plot(self, x as f32, inter_y.floor() + 1.0, fpart(inter_y));
+            inter_y += gradient;
+        }
+    }
+
+    pub fn draw_rect(&mut self, rect: impl Into<FloatRect>) {
+        let float_rect = rect.into();
+        if self.record(|| Shape::Rect(float_rect)) {
+            return;
+        }
+        let (left, top) = (float_rect.x(), float_rect.y());
+        let (right, bottom) = (left + float_rect.width, top + float_rect.height);
+
+        let offset = match self.state.matrix.as_translation() {
+            Some(offset) => offset,
+            None => {
+                let corners = vec![(left, top), (right, top), (right, bottom), (left, bottom)];
+                self.draw_polylines(&[(corners, true)]);
+                return;
+            }
+        };
+        let rect = FloatRect::new(
+            FloatPoint::new(left + offset.0, top + offset.1),
+            float_rect.width,
+            float_rect.height,
+        )
+        .round();
+
+        if self.state.fill.is_some() {
             for x in rect.x()..=rect.x() + rect.width {
                 for y in rect.y()..=rect.y() + rect.height {
                     self.set_pixel_from_color_source(x, y, ColorSource::Fill);
@@ -192,95 +736,332 @@ impl Canvas {
             }
         }
 
-        if self.stroke.is_some() {
-            let top_right = IntPoint::new(rect.x() + rect.width, rect.y());
-            let lower_left = IntPoint::new(rect.x(), rect.y() + rect.height);
-            let lower_right = IntPoint::new(rect.x() + rect.width, rect.y() + rect.height);
-
-            self.draw_line(&rect.location, &top_right);
-            self.draw_line(&top_right, &lower_right);
-            self.draw_line(&lower_right, &lower_left);
-            self.draw_line(&lower_left, &rect.location);
+        if self.state.stroke.is_some() && self.is_thick_stroke() {
+            let corners = vec![(left, top), (right, top), (right, bottom), (left, bottom)];
+            self.stroke_polylines(&[(corners, true)]);
+        } else if self.state.stroke.is_some() && !self.state.smooth {
+            // walk the outline directly so translucent corners are not blended twice
+            let (left, top) = (rect.x(), rect.y());
+            let (right, bottom) = (left + rect.width, top + rect.height);
+            for x in left..=right {
+                self.set_pixel_from_color_source(x, top, ColorSource::Stroke);
+                if bottom != top {
+                    self.set_pixel_from_color_source(x, bottom, ColorSource::Stroke);
+                }
+            }
+            for y in top + 1..bottom {
+                self.set_pixel_from_color_source(left, y, ColorSource::Stroke);
+                if right != left {
+                    self.set_pixel_from_color_source(right, y, ColorSource::Stroke);
+                }
+            }
+        } else if self.state.stroke.is_some() {
+            let corners = [(left, top), (right, top), (right, bottom), (left, bottom)]
+                .map(|(x, y)| FloatPoint::new(x, y));
+            for side in 0..4 {
+                self.stroke_line(corners[side], corners[(side + 1) % 4]);
+            }
         }
     }
 
-    pub fn draw_square(&mut self, origin: &IntPoint, size: isize) {
-        let rect = IntRect::new(origin.clone(), size, size);
-        self.draw_rect(&rect);
+    pub fn draw_square(&mut self, origin: impl Into<FloatPoint>, size: f32) {
+        let rect = FloatRect::new(origin.into(), size, size);
+        self.draw_rect(rect);
     }
 
-    pub fn draw_text(&mut self, origin: &IntPoint, msg: &str) {
-        if self.fill.is_none() {
+    /// Draws `msg` anchored at `origin` according to `text_align`, lines are
+    /// split at `\n`.
+    pub fn draw_text(&mut self, origin: impl Into<FloatPoint>, msg: &str) {
+        let origin = origin.into();
+        if self.state.fill.is_none() {
             return;
         }
 
-        let font_weight = match self.text_style.weight {
-            FontWeight::Light => noto_sans_mono_bitmap::FontWeight::Light,
-            FontWeight::Regular => noto_sans_mono_bitmap::FontWeight::Regular,
-            FontWeight::Bold => noto_sans_mono_bitmap::FontWeight::Bold,
-        };
+        let lines: Vec<&str> = msg.split('\n').collect();
+        self.draw_text_lines(origin, &lines, None);
+    }
 
-        let bitmap_height = match self.text_style.size {
-            0..=14 => BitmapHeight::Size14,
-            15..=16 => BitmapHeight::Size16,
-            17..=18 => BitmapHeight::Size18,
-            19..=20 => BitmapHeight::Size20,
-            21..=22 => BitmapHeight::Size22,
-            23..=24 => BitmapHeight::Size24,
-            25..=32 => BitmapHeight::Size32,
-            33..=64 => BitmapHeight::Size64,
-            65..=usize::MAX => BitmapHeight::Size64,
-            _ => BitmapHeight::Size64,
+    /// Draws `msg` word-wrapped to the width of `bounds` and aligned inside
+    /// it, lines that do not fit below are left out.
+    pub fn draw_text_box(&mut self, bounds: impl Into<FloatRect>, msg: &str) {
+        let bounds = bounds.into();
+        if self.state.fill.is_none() {
+            return;
+        }
+
+        let face = self.state.text_style.font_face();
+        let line_height = face.line_height();
+        let line_advance = line_height * self.state.text_style.line_spacing;
+        let mut lines = face.wrap(msg, bounds.width);
+        let fitting = if bounds.height < line_height {
+            0
+        } else if line_advance > 0.0 {
+            (((bounds.height - line_height) / line_advance).floor() as usize).saturating_add(1)
+        } else {
+            // lines without a positive advance all overlap the first one
+            lines.len()
         };
-        let char_width = get_bitmap_width(font_weight, bitmap_height);
-        let mut y_origin = origin.y;
+        lines.truncate(fitting);
 
-        let lines = msg.split('\n');
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        self.draw_text_lines(bounds.location, &lines, Some((bounds.width, bounds.height)));
+    }
 
-        for line in lines {
-            for (char_i, char) in line.chars().enumerate() {
-                let bitmap_char = get_bitmap(char, font_weight, bitmap_height).unwrap_or(
-                    //Fall back to whitespace for unknown char
-                    get_bitmap(' ', font_weight, bitmap_height).unwrap(),
-                );
+    fn draw_text_lines(
+        &mut self,
+        origin: FloatPoint,
+        lines: &[&str],
+        box_size: Option<(f32, f32)>,
+    ) {
+        if lines.is_empty() {
+            return;
+        }
 
-                for (row_i, row) in bitmap_char.bitmap().iter().enumerate() {
-                    for (col_i, intensity) in row.iter().enumerate() {
-                        let x = origin.x + char_i as isize * char_width as isize + col_i as isize;
-                        let y = y_origin + row_i as isize;
+        if self.recording.is_some() {
+            let style = &self.state.text_style;
+            let face = style.font_face();
+            let runs = lines
+                .iter()
+                .zip(self.layout_text_lines(&face, lines, box_size))
+                .map(|(line, pt)| TextRun {
+                    position: FloatPoint::new(origin.x + pt.x, origin.y + pt.y + face.ascent()),
+                    text: line.to_string(),
+                    width: face.line_width(line),
+                })
+                .collect();
+            let recorded = Shape::Text {
+                runs,
+                font: style.font.clone(),
+                size: style.size,
+                weight: style.weight,
+            };
+            if self.record(|| recorded) {
+                return;
+            }
+        }
+
+        let (mask_origin, mask, mask_width, mask_height) = {
+            let face = self.state.text_style.font_face();
+            let positions = self.layout_text_lines(&face, lines, box_size);
+            let line_height = face.line_height();
+
+            // glyphs may overhang their advance box, leave a margin around the lines
+            let margin = (line_height / 4.0).ceil();
+            let left = positions
+                .iter()
+                .map(|pt| pt.x)
+                .fold(f32::MAX, f32::min)
+                .floor();
+            let top = positions[0].y.floor();
+            let right = lines
+                .iter()
+                .zip(&positions)
+                .map(|(line, pt)| pt.x + face.line_width(line))
+                .fold(f32::MIN, f32::max);
+            let bottom = positions.last().unwrap().y + line_height;
+
+            let mask_width = (right - left + 2.0 * margin).ceil() as usize;
+            let mask_height = (bottom - top + 2.0 * margin).ceil() as usize;
+            let mut mask = vec![0u8; mask_width * mask_height];
+            for (line, pt) in lines.iter().zip(&positions) {
+                let x = pt.x - left + margin;
+                let y = pt.y - top + margin;
+                face.render_line(line, &mut mask, mask_width, x, y);
+            }
+
+            let mask_origin = FloatPoint::new(origin.x + left - margin, origin.y + top - margin);
+            (mask_origin, mask, mask_width, mask_height)
+        };
+
+        self.blit_mask(mask_origin, &mask, mask_width, mask_height);
+    }
 
-                        // TODO: blitting with opacity
-                        if *intensity > 80 {
-                            self.set_pixel_from_color_source(x, y, ColorSource::Fill);
-                        }
+    /// Blends the fill color through a coverage mask whose top left corner is
+    /// placed at `origin` in user space.
+    fn blit_mask(&mut self, origin: FloatPoint, mask: &[u8], width: usize, height: usize) {
+        if let Some((dx, dy)) = self.state.matrix.as_translation() {
+            let origin = FloatPoint::new(origin.x + dx, origin.y + dy).round();
+            for y in 0..height {
+                for x in 0..width {
+                    let intensity = mask[y * width + x];
+                    if intensity > 0 {
+                        self.blend_pixel_from_color_source(
+                            origin.x + x as isize,
+                            origin.y + y as isize,
+                            ColorSource::Fill,
+                            intensity as f32 / 255.0,
+                        );
                     }
                 }
             }
-            y_origin += bitmap_height as isize;
+            return;
+        }
+
+        let to_device = self
+            .state
+            .matrix
+            .multiply(&Transform::translation(origin.x, origin.y));
+        let to_mask = match to_device.invert() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+
+        // map every device pixel inside the transformed bounds back into the mask
+        let corners = [
+            (0.0, 0.0),
+            (width as f32, 0.0),
+            (0.0, height as f32),
+            (width as f32, height as f32),
+        ]
+        .map(|corner| to_device.apply_tuple(corner));
+        let min_x = corners.iter().map(|c| c.0).fold(f32::MAX, f32::min).floor();
+        let max_x = corners.iter().map(|c| c.0).fold(f32::MIN, f32::max).ceil();
+        let min_y = corners.iter().map(|c| c.1).fold(f32::MAX, f32::min).floor();
+        let max_y = corners.iter().map(|c| c.1).fold(f32::MIN, f32::max).ceil();
+        let min_x = min_x.max(0.0) as isize;
+        let min_y = min_y.max(0.0) as isize;
+        let max_x = max_x.min(self.pixelbuffer.width() as f32) as isize;
+        let max_y = max_y.min(self.pixelbuffer.height() as f32) as isize;
+
+        let sample = |x: isize, y: isize| -> f32 {
+            if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+                0.0
+            } else {
+                mask[y as usize * width + x as usize] as f32 / 255.0
+            }
+        };
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (u, v) = to_mask.apply_tuple((x as f32 + 0.5, y as f32 + 0.5));
+                let coverage = if self.state.smooth {
+                    let (u, v) = (u - 0.5, v - 0.5);
+                    let (x0, y0) = (u.floor(), v.floor());
+                    let (fx, fy) = (u - x0, v - y0);
+                    let (x0, y0) = (x0 as isize, y0 as isize);
+                    let top = sample(x0, y0) * (1.0 - fx) + sample(x0 + 1, y0) * fx;
+                    let bottom = sample(x0, y0 + 1) * (1.0 - fx) + sample(x0 + 1, y0 + 1) * fx;
+                    top * (1.0 - fy) + bottom * fy
+                } else {
+                    sample(u.floor() as isize, v.floor() as isize)
+                };
+                if coverage > 0.0 {
+                    self.blend_pixel_from_color_source(x, y, ColorSource::Fill, coverage);
+                }
+            }
         }
     }
 
-    pub fn draw_path(&mut self, path: &Path) {
-        if self.stroke.is_some() {
-            let mut cursor = IntPoint::new(0, 0);
+    /// Draws the `source` region of the image, or all of it if `None`,
+    /// scaled into `dest`.
+    pub fn draw_image(
+        &mut self,
+        image: &Image,
+        dest: impl Into<FloatRect>,
+        source: Option<IntRect>,
+    ) {
+        let dest = dest.into();
+        let source = match source {
+            Some(source) => {
+                // keep the requested region inside the image
+                let left = source.x().clamp(0, image.width() as isize);
+                let top = source.y().clamp(0, image.height() as isize);
+                let right = (source.x() + source.width).clamp(left, image.width() as isize);
+                let bottom = (source.y() + source.height).clamp(top, image.height() as isize);
+                IntRect::new(IntPoint::new(left, top), right - left, bottom - top)
+            }
+            None => image.bounds(),
+        };
+        if dest.width == 0.0 || dest.height == 0.0 || source.width <= 0 || source.height <= 0 {
+            return;
+        }
+        let tint = self.state.tint;
+        let sampling = self.state.image_sampling;
+        if self.record(|| Shape::Image {
+            image: Arc::new(image.cropped(&source, tint)),
+            dest,
+            sampling,
+        }) {
+            return;
+        }
 
-            for segment in path.segments() {
-                match segment {
-                    PathSegment::MoveTo(pt) => {
-                        cursor = pt.clone();
-                    }
-                    PathSegment::LineTo(pt) => {
-                        self.draw_line(&cursor, pt);
-                        cursor = pt.clone();
-                    }
+        let to_device = self
+            .state
+            .matrix
+            .multiply(&Transform::translation(dest.x(), dest.y()))
+            .multiply(&Transform::scaling(
+                dest.width / source.width as f32,
+                dest.height / source.height as f32,
+            ));
+        let to_image = match to_device.invert() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+
+        let (width, height) = (source.width as f32, source.height as f32);
+        let corners = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)]
+            .map(|corner| to_device.apply_tuple(corner));
+        let min_x = corners.iter().map(|c| c.0).fold(f32::MAX, f32::min);
+        let max_x = corners.iter().map(|c| c.0).fold(f32::MIN, f32::max);
+        let min_y = corners.iter().map(|c| c.1).fold(f32::MAX, f32::min);
+        let max_y = corners.iter().map(|c| c.1).fold(f32::MIN, f32::max);
+        let min_x = min_x.round().max(0.0) as isize;
+        let min_y = min_y.round().max(0.0) as isize;
+        let max_x = max_x.round().min(self.pixelbuffer.width() as f32) as isize;
+        let max_y = max_y.round().min(self.pixelbuffer.height() as f32) as isize;
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (u, v) = to_image.apply_tuple((x as f32 + 0.5, y as f32 + 0.5));
+                if u < 0.0 || v < 0.0 || u >= width || v >= height {
+                    continue;
                 }
+                let color = tinted(
+                    image.sample(
+                        u + source.x() as f32,
+                        v + source.y() as f32,
+                        &source,
+                        sampling,
+                    ),
+                    tint,
+                );
+                self.pixelbuffer
+                    .blend_pixel(x, y, &color, 1.0, self.state.blend_mode);
             }
         }
     }
 
-    pub fn draw_ellipse(&mut self, center: &IntPoint, width: usize, height: usize) {
-        let h = height as isize;
-        let w = width as isize;
+    /// Strokes the path and fills its closed sub-paths, open ones are only
+    /// stroked.
+    pub fn draw_path(&mut self, path: &Path) {
+        if self.record(|| Shape::Path(path.clone())) {
+            return;
+        }
+        let tolerance = self.state.curve_tolerance / self.state.matrix.scale_factor().max(0.001);
+        self.draw_polylines(&path.polylines(tolerance));
+    }
+
+    pub fn draw_ellipse(&mut self, center: impl Into<FloatPoint>, width: f32, height: f32) {
+        let float_center = center.into();
+        let recorded = Shape::Ellipse {
+            center: float_center,
+            radius_x: width,
+            radius_y: height,
+        };
+        if self.record(|| recorded) {
+            return;
+        }
+        let center = match self.state.matrix.as_translation() {
+            Some(offset) => FloatPoint::new(float_center.x + offset.0, float_center.y + offset.1),
+            None => {
+                let outline = stroke::ellipse((float_center.x, float_center.y), width, height);
+                self.draw_polylines(&[(outline, true)]);
+                return;
+            }
+        }
+        .round();
+        let h = height.round() as isize;
+        let w = width.round() as isize;
         let hh = h * h;
         let ww = w * w;
         let hhww = hh * ww;
@@ -305,10 +1086,205 @@ impl Canvas {
                 self.set_pixel_from_color_source(center.x + x, center.y - y, ColorSource::Fill);
                 self.set_pixel_from_color_source(center.x + x, center.y + y, ColorSource::Fill);
             }
-            self.set_pixel_from_color_source(center.x + x0, center.y - y, ColorSource::Stroke);
-            self.set_pixel_from_color_source(center.x + x0, center.y + y, ColorSource::Stroke);
-            self.set_pixel_from_color_source(center.x - x0, center.y + y, ColorSource::Stroke);
-            self.set_pixel_from_color_source(center.x - x0, center.y - y, ColorSource::Stroke);
+            if !self.state.smooth && !self.is_thick_stroke() {
+                self.set_pixel_from_color_source(center.x + x0, center.y - y, ColorSource::Stroke);
+                self.set_pixel_from_color_source(center.x + x0, center.y + y, ColorSource::Stroke);
+                self.set_pixel_from_color_source(center.x - x0, center.y + y, ColorSource::Stroke);
+                self.set_pixel_from_color_source(center.x - x0, center.y - y, ColorSource::Stroke);
+            }
         }
+
+        if self.is_thick_stroke() {
+            if self.state.stroke.is_some() {
+                let contours = self.transform_contours(stroke::ellipse_ring(
+                    (float_center.x, float_center.y),
+                    width,
+                    height,
+                    self.state.stroke_style.weight,
+                ));
+                self.fill_contours_from_color_source(
+                    &contours,
+                    FillRule::NonZero,
+                    ColorSource::Stroke,
+                );
+            }
+        } else if self.state.smooth {
+            self.draw_ellipse_outline_smooth(&center, width, height);
+        }
+    }
+
+    /// Wu style ellipse outline, each octant is stepped along its major axis and the
+    /// two pixels straddling the exact curve share the coverage.
+    fn draw_ellipse_outline_smooth(&mut self, center: &IntPoint, radius_x: f32, radius_y: f32) {
+        if self.state.stroke.is_none() {
+            return;
+        }
+
+        let plot_mirrored = |canvas: &mut Canvas, dx: isize, dy: isize, coverage: f32| {
+            let mut points = vec![(dx, dy)];
+            if dx != 0 {
+                points.push((-dx, dy));
+            }
+            if dy != 0 {
+                points.push((dx, -dy));
+                if dx != 0 {
+                    points.push((-dx, -dy));
+                }
+            }
+            for (px, py) in points {
+                canvas.blend_pixel_from_color_source(
+                    center.x + px,
+                    center.y + py,
+                    ColorSource::Stroke,
+                    coverage,
+                );
+            }
+        };
+
+        let rxx = radius_x * radius_x;
+        let ryy = radius_y * radius_y;
+        let diagonal = (rxx + ryy).sqrt();
+        if diagonal == 0.0 {
+            plot_mirrored(self, 0, 0, 1.0);
+            return;
+        }
+
+        let x_limit = (rxx / diagonal).round() as isize;
+        for x in 0..=x_limit {
+            let y = radius_y * (1.0 - (x * x) as f32 / rxx).max(0.0).sqrt();
+            let error = y - y.floor();
+            plot_mirrored(self, x, y.floor() as isize, 1.0 - error);
+            plot_mirrored(self, x, y.floor() as isize + 1, error);
+        }
+
+        let y_limit = (ryy / diagonal).round() as isize;
+        for y in 0..y_limit {
+            let x = radius_x * (1.0 - (y * y) as f32 / ryy).max(0.0).sqrt();
+            let error = x - x.floor();
+            plot_mirrored(self, x.floor() as isize, y, 1.0 - error);
+            plot_mirrored(self, x.floor() as isize + 1, y, error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display_list::RecordedShape;
+
+    fn pixel(canvas: &Canvas, x: usize, y: usize) -> [u8; 4] {
+        let index = (y * canvas.width() + x) * 4;
+        canvas.as_raw_buffer()[index..index + 4].try_into().unwrap()
+    }
+
+    fn triangle(closed: bool) -> Path {
+        let mut path = Path::new();
+        path.move_to((2.0, 2.0));
+        path.line_to((17.0, 2.0));
+        path.line_to((2.0, 17.0));
+        if closed {
+            path.close();
+        }
+        path
+    }
+
+    #[test]
+    fn canvas_can_move_between_threads() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Canvas>();
+        assert_send::<Font>();
+    }
+
+    #[test]
+    fn only_closed_paths_are_filled() {
+        let mut canvas = Canvas::new(20, 20);
+        canvas.fill(Color::rgb(255, 0, 0));
+        canvas.draw_path(&triangle(false));
+        assert_eq!(pixel(&canvas, 6, 6), [255, 255, 255, 255]);
+        assert_eq!(pixel(&canvas, 8, 2), [0, 0, 0, 255]);
+
+        canvas.draw_path(&triangle(true));
+        assert_eq!(pixel(&canvas, 6, 6), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn replayed_images_match_drawn_ones() {
+        let mut image = Image::new(3, 2);
+        image.set_pixel(0, 0, Color::rgb(255, 0, 0));
+        image.set_pixel(2, 1, Color::rgba(0, 0, 255, 100));
+
+        let mut canvas = Canvas::new(20, 20);
+        canvas.begin_recording(Recording::RasterAndVector);
+        canvas.translate(10.0, 10.0);
+        canvas.rotate(0.5);
+        canvas.tint(Color::rgb(255, 128, 0));
+        canvas.draw_image(
+            &image,
+            FloatRect::new(FloatPoint::new(-6.0, -4.0), 12.0, 8.0),
+            None,
+        );
+        // a tint applied before interpolating may round differently
+        canvas.no_tint();
+        canvas.image_sampling(ImageSampling::Bilinear);
+        canvas.draw_image(
+            &image,
+            FloatRect::new(FloatPoint::new(0.0, 0.0), 8.0, 8.0),
+            Some(IntRect::new(IntPoint::new(1, 0), 2, 2)),
+        );
+
+        let mut replayed = Canvas::new(20, 20);
+        replayed.replay(canvas.display_list().unwrap());
+        assert_eq!(replayed.as_raw_buffer(), canvas.as_raw_buffer());
+    }
+
+    #[test]
+    fn replay_restores_blend_mode_and_curve_tolerance() {
+        let mut canvas = Canvas::new(20, 20);
+        canvas.begin_recording(Recording::RasterAndVector);
+        canvas.fill(Color::rgb(100, 0, 0));
+        canvas.draw_rect(FloatRect::new(FloatPoint::new(0.0, 0.0), 12.0, 12.0));
+        canvas.blend_mode(BlendMode::Add);
+        canvas.curve_tolerance(4.0);
+        canvas.fill(Color::rgb(100, 50, 0));
+        canvas.draw_ellipse(FloatPoint::new(10.0, 10.0), 8.0, 6.0);
+        assert_eq!(pixel(&canvas, 10, 10), [200, 50, 0, 255]);
+
+        let display_list = canvas.display_list().unwrap();
+        let style = display_list.shapes()[1].style;
+        assert_eq!(style.blend_mode, BlendMode::Add);
+        assert_eq!(style.curve_tolerance, 4.0);
+
+        let mut replayed = Canvas::new(20, 20);
+        replayed.replay(display_list);
+        assert_eq!(replayed.as_raw_buffer(), canvas.as_raw_buffer());
+        assert_eq!(replayed.state.blend_mode, BlendMode::SourceOver);
+    }
+
+    fn text_box_lines(line_spacing: f32, height: f32) -> usize {
+        let mut canvas = Canvas::new(100, 100);
+        canvas.begin_recording(Recording::VectorOnly);
+        canvas.fill(Color::rgb(0, 0, 0));
+        canvas.line_spacing(line_spacing);
+        let line_height = canvas.state.text_style.font_face().line_height();
+        canvas.draw_text_box(
+            FloatRect::new(FloatPoint::new(0.0, 0.0), 100.0, height * line_height),
+            "one\ntwo\nthree\nfour",
+        );
+        match &canvas.display_list().unwrap().shapes().first() {
+            Some(RecordedShape {
+                shape: Shape::Text { runs, .. },
+                ..
+            }) => runs.len(),
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn text_box_keeps_the_lines_that_fit() {
+        assert_eq!(text_box_lines(1.0, 2.5), 2);
+        assert_eq!(text_box_lines(2.0, 3.0), 2);
+        assert_eq!(text_box_lines(1.0, 0.5), 0);
+        assert_eq!(text_box_lines(0.0, 1.0), 4);
+        assert_eq!(text_box_lines(-1.0, 1.0), 4);
     }
 }
@@ -1,51 +1,468 @@
-#[derive(Clone, Copy, Debug)]
-#[repr(packed)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
 pub struct Color {
     pub red: u8,
     pub green: u8,
     pub blue: u8,
+    pub alpha: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HexColorError {
+    MissingHash,
+    /// Length of the whole literal including the `#`.
+    InvalidLength(usize),
+    /// Byte offset of the first character that is not a hex digit.
+    InvalidDigit {
+        position: usize,
+    },
+}
+
+/// Creates a `Color` from a hex literal like `color!("#ff8800")`, the literal
+/// is validated at compile time and malformed ones fail the build.
+#[macro_export]
+macro_rules! color {
+    ($hex:literal) => {{
+        const COLOR: $crate::color::Color = match $crate::color::Color::hex($hex) {
+            Ok(color) => color,
+            Err(_) => panic!(concat!("invalid hex color literal ", $hex)),
+        };
+        COLOR
+    }};
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    SourceOver,
+    Add,
+    Multiply,
+    Screen,
+    Lighten,
+    Darken,
+    Difference,
+}
+
+/// Color space and value ranges used by `Canvas::color`, mirroring
+/// Processing's `colorMode`. `ranges` holds the maximum of each of the three
+/// components followed by the maximum of the alpha value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorMode {
+    Rgb { ranges: [f32; 4] },
+    Hsb { ranges: [f32; 4] },
+    Hsl { ranges: [f32; 4] },
+}
+
+impl Default for ColorMode {
+    fn default() -> Self {
+        ColorMode::Rgb {
+            ranges: [255.0, 255.0, 255.0, 255.0],
+        }
+    }
+}
+
+impl ColorMode {
+    pub fn ranges(&self) -> [f32; 4] {
+        match self {
+            ColorMode::Rgb { ranges } | ColorMode::Hsb { ranges } | ColorMode::Hsl { ranges } => {
+                *ranges
+            }
+        }
+    }
+
+    /// Builds a color from components given in this mode's space and ranges.
+    pub fn color(&self, v1: f32, v2: f32, v3: f32, alpha: f32) -> Color {
+        let space_color: fn(f32, f32, f32) -> Color = match self {
+            ColorMode::Rgb { .. } => |r, g, b| {
+                let channel = |value: f32| (value * 255.0).round() as u8;
+                Color::rgb(channel(r), channel(g), channel(b))
+            },
+            ColorMode::Hsb { .. } => |h, s, v| Color::hsv(h * 360.0, s, v),
+            ColorMode::Hsl { .. } => |h, s, l| Color::hsl(h * 360.0, s, l),
+        };
+        let ranges = self.ranges();
+        let normalize = |value: f32, range: f32| {
+            if range > 0.0 {
+                (value / range).clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        };
+        let alpha = (normalize(alpha, ranges[3]) * 255.0).round() as u8;
+        space_color(
+            normalize(v1, ranges[0]),
+            normalize(v2, ranges[1]),
+            normalize(v3, ranges[2]),
+        )
+        .with_alpha(alpha)
+    }
+}
+
+impl BlendMode {
+    fn mix(&self, backdrop: f32, source: f32) -> f32 {
+        match self {
+            BlendMode::SourceOver => source,
+            BlendMode::Add => (backdrop + source).min(1.0),
+            BlendMode::Multiply => backdrop * source,
+            BlendMode::Screen => backdrop + source - backdrop * source,
+            BlendMode::Lighten => backdrop.max(source),
+            BlendMode::Darken => backdrop.min(source),
+            BlendMode::Difference => (backdrop - source).abs(),
+        }
+    }
 }
 
 impl Color {
-    pub fn rgb(red: u8, green: u8, blue: u8) -> Self {
-        Color { red, green, blue }
-    }
-
-    pub fn hex(hex: &str) -> Result<Self, ()> {
-        if !hex.starts_with('#') {
-            return Err(());
-        }
-        if let Ok(color_value) = u32::from_str_radix(hex.trim_start_matches('#'), 16) {
-            eprintln!("cl: {}", color_value);
-            return match hex.len() {
-                4 => {
-                    let r = ((color_value >> 8) & 0xF) as u8;
-                    let g = ((color_value >> 4) & 0xF) as u8;
-                    let b = (color_value & 0xF) as u8;
-                    Ok(Color::rgb(r | r << 4, g | g << 4, b | b << 4))
-                }
-                7 => Ok(Color::rgb(
-                    ((color_value >> 16) & 0xFF) as u8,
-                    ((color_value >> 8) & 0xFF) as u8,
-                    (color_value & 0xFF) as u8,
-                )),
-                _ => Err(()),
+    pub const fn rgb(red: u8, green: u8, blue: u8) -> Self {
+        Color::rgba(red, green, blue, 255)
+    }
+
+    pub const fn rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Self {
+        Color {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+
+    /// Parses `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`. Use the `color!`
+    /// macro for literals to have them checked at compile time.
+    pub const fn hex(hex: &str) -> Result<Self, HexColorError> {
+        let bytes = hex.as_bytes();
+        if bytes.is_empty() || bytes[0] != b'#' {
+            return Err(HexColorError::MissingHash);
+        }
+
+        let digit_count = bytes.len() - 1;
+        let short = digit_count == 3 || digit_count == 4;
+        if !short && digit_count != 6 && digit_count != 8 {
+            return Err(HexColorError::InvalidLength(bytes.len()));
+        }
+
+        let mut components = [0u8; 4];
+        let mut index = 1;
+        while index < bytes.len() {
+            let value = match bytes[index] {
+                digit @ b'0'..=b'9' => digit - b'0',
+                digit @ b'a'..=b'f' => digit - b'a' + 10,
+                digit @ b'A'..=b'F' => digit - b'A' + 10,
+                _ => return Err(HexColorError::InvalidDigit { position: index }),
             };
+            if short {
+                components[index - 1] = value << 4 | value;
+            } else {
+                let component = (index - 1) / 2;
+                components[component] = components[component] << 4 | value;
+            }
+            index += 1;
         }
-        Err(())
+        if digit_count == 3 || digit_count == 6 {
+            components[3] = 255;
+        }
+
+        Ok(Color::rgba(
+            components[0],
+            components[1],
+            components[2],
+            components[3],
+        ))
+    }
+
+    /// Hue in degrees, saturation and lightness in the range 0.0 to 1.0.
+    pub fn hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let saturation = saturation.clamp(0.0, 1.0);
+        let lightness = lightness.clamp(0.0, 1.0);
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        Color::from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+    }
+
+    /// Hue in degrees, saturation and value in the range 0.0 to 1.0.
+    pub fn hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let saturation = saturation.clamp(0.0, 1.0);
+        let value = value.clamp(0.0, 1.0);
+        let chroma = value * saturation;
+        Color::from_hue_chroma(hue, chroma, value - chroma)
+    }
+
+    fn from_hue_chroma(hue: f32, chroma: f32, offset: f32) -> Self {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let second = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (red, green, blue) = match sector as u32 {
+            0 => (chroma, second, 0.0),
+            1 => (second, chroma, 0.0),
+            2 => (0.0, chroma, second),
+            3 => (0.0, second, chroma),
+            4 => (second, 0.0, chroma),
+            _ => (chroma, 0.0, second),
+        };
+        let channel = |value: f32| ((value + offset) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Color::rgb(channel(red), channel(green), channel(blue))
+    }
+
+    /// Hue in degrees along with the maximum, minimum and chroma of the
+    /// normalized channels.
+    fn hue_and_extrema(&self) -> (f32, f32, f32, f32) {
+        let (red, green, blue) = (
+            self.red as f32 / 255.0,
+            self.green as f32 / 255.0,
+            self.blue as f32 / 255.0,
+        );
+        let max = red.max(green).max(blue);
+        let min = red.min(green).min(blue);
+        let chroma = max - min;
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == red {
+            60.0 * ((green - blue) / chroma).rem_euclid(6.0)
+        } else if max == green {
+            60.0 * ((blue - red) / chroma + 2.0)
+        } else {
+            60.0 * ((red - green) / chroma + 4.0)
+        };
+        (hue, max, min, chroma)
+    }
+
+    /// Returns hue in degrees, saturation and lightness in the range 0.0 to 1.0.
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let (hue, max, min, chroma) = self.hue_and_extrema();
+        let lightness = (max + min) / 2.0;
+        let saturation = if lightness <= 0.0 || lightness >= 1.0 {
+            0.0
+        } else {
+            chroma / (1.0 - (2.0 * lightness - 1.0).abs())
+        };
+        (hue, saturation, lightness)
+    }
+
+    /// Returns hue in degrees, saturation and value in the range 0.0 to 1.0.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (hue, max, _, chroma) = self.hue_and_extrema();
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+        (hue, saturation, max)
+    }
+
+    fn map_hsl(&self, map: impl Fn(f32, f32, f32) -> (f32, f32, f32)) -> Color {
+        let (hue, saturation, lightness) = self.to_hsl();
+        let (hue, saturation, lightness) = map(hue, saturation, lightness);
+        Color::hsl(hue, saturation, lightness).with_alpha(self.alpha)
+    }
+
+    pub fn rotate_hue(&self, degrees: f32) -> Color {
+        self.map_hsl(|h, s, l| (h + degrees, s, l))
+    }
+
+    /// Increases the HSL saturation by `amount`, given in the range 0.0 to 1.0.
+    pub fn saturate(&self, amount: f32) -> Color {
+        self.map_hsl(|h, s, l| (h, s + amount, l))
+    }
+
+    pub fn desaturate(&self, amount: f32) -> Color {
+        self.saturate(-amount)
+    }
+
+    /// Increases the HSL lightness by `amount`, given in the range 0.0 to 1.0.
+    pub fn lighten(&self, amount: f32) -> Color {
+        self.map_hsl(|h, s, l| (h, s, l + amount))
+    }
+
+    pub fn darken(&self, amount: f32) -> Color {
+        self.lighten(-amount)
+    }
+
+    pub fn with_alpha(&self, alpha: u8) -> Color {
+        Color::rgba(self.red, self.green, self.blue, alpha)
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.alpha == 255
     }
 
     pub fn lerp_to(&self, to: &Color, amount: f32) -> Color {
         let lerp_component = |start: u8, end: u8, amount: f32| -> u8 {
             let dx = end as f32 - start as f32;
             let r = start as f32 + dx * amount;
-            r.clamp(0.0, 254.0) as u8
+            r.round().clamp(0.0, 255.0) as u8
         };
 
-        Color::rgb(
+        Color::rgba(
             lerp_component(self.red, to.red, amount),
             lerp_component(self.green, to.green, amount),
             lerp_component(self.blue, to.blue, amount),
+            lerp_component(self.alpha, to.alpha, amount),
         )
     }
+
+    /// Composites this color onto `backdrop` following the W3C compositing
+    /// model, `coverage` scales the source alpha for partially covered pixels.
+    pub fn composite_over(&self, backdrop: &Color, mode: BlendMode, coverage: f32) -> Color {
+        let source_alpha = self.alpha as f32 / 255.0 * coverage.clamp(0.0, 1.0);
+        let backdrop_alpha = backdrop.alpha as f32 / 255.0;
+        let result_alpha = source_alpha + backdrop_alpha * (1.0 - source_alpha);
+        if result_alpha <= 0.0 {
+            return Color::rgba(0, 0, 0, 0);
+        }
+
+        let composite_component = |backdrop: u8, source: u8| -> u8 {
+            let backdrop = backdrop as f32 / 255.0;
+            let source = source as f32 / 255.0;
+            let mixed =
+                (1.0 - backdrop_alpha) * source + backdrop_alpha * mode.mix(backdrop, source);
+            let premultiplied =
+                source_alpha * mixed + backdrop_alpha * backdrop * (1.0 - source_alpha);
+            (premultiplied / result_alpha * 255.0)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+
+        Color::rgba(
+            composite_component(backdrop.red, self.red),
+            composite_component(backdrop.green, self.green),
+            composite_component(backdrop.blue, self.blue),
+            (result_alpha * 255.0).round() as u8,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_literals_of_every_length() {
+        assert_eq!(Color::hex("#f80"), Ok(Color::rgb(0xff, 0x88, 0x00)));
+        assert_eq!(Color::hex("#f808"), Ok(Color::rgba(0xff, 0x88, 0x00, 0x88)));
+        assert_eq!(Color::hex("#Ff8001"), Ok(Color::rgb(0xff, 0x80, 0x01)));
+        assert_eq!(
+            Color::hex("#ff800140"),
+            Ok(Color::rgba(0xff, 0x80, 0x01, 0x40))
+        );
+    }
+
+    #[test]
+    fn color_macro_matches_hex() {
+        assert_eq!(crate::color!("#abc"), Color::rgb(0xaa, 0xbb, 0xcc));
+        assert_eq!(crate::color!("#abcd"), Color::rgba(0xaa, 0xbb, 0xcc, 0xdd));
+        assert_eq!(crate::color!("#123456"), Color::rgb(0x12, 0x34, 0x56));
+        assert_eq!(
+            crate::color!("#12345678"),
+            Color::rgba(0x12, 0x34, 0x56, 0x78)
+        );
+    }
+
+    #[test]
+    fn malformed_hex_literals() {
+        assert_eq!(Color::hex(""), Err(HexColorError::MissingHash));
+        assert_eq!(Color::hex("ff8800"), Err(HexColorError::MissingHash));
+        assert_eq!(Color::hex("#"), Err(HexColorError::InvalidLength(1)));
+        assert_eq!(Color::hex("#ff880"), Err(HexColorError::InvalidLength(6)));
+        assert_eq!(
+            Color::hex("#ff8800aa0"),
+            Err(HexColorError::InvalidLength(10))
+        );
+        assert_eq!(
+            Color::hex("#ff8g00"),
+            Err(HexColorError::InvalidDigit { position: 4 })
+        );
+        assert_eq!(
+            Color::hex("#é00"),
+            Err(HexColorError::InvalidDigit { position: 1 })
+        );
+    }
+
+    #[test]
+    fn lerp_keeps_opaque_colors_opaque() {
+        let start = Color::rgb(0, 100, 255);
+        let end = Color::rgb(255, 201, 0);
+        assert_eq!(start.lerp_to(&end, 0.0), start);
+        assert_eq!(start.lerp_to(&end, 1.0), end);
+        let middle = start.lerp_to(&end, 0.5);
+        assert!(middle.is_opaque());
+        assert_eq!(middle, Color::rgb(128, 151, 128));
+        assert_eq!(start.lerp_to(&end, 2.0), Color::rgb(255, 255, 0));
+    }
+
+    fn assert_components(actual: (f32, f32, f32), expected: (f32, f32, f32)) {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-3;
+        assert!(
+            close(actual.0, expected.0)
+                && close(actual.1, expected.1)
+                && close(actual.2, expected.2),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn known_hsl_and_hsv_values() {
+        assert_eq!(Color::hsl(0.0, 1.0, 0.5), Color::rgb(255, 0, 0));
+        assert_eq!(Color::hsl(120.0, 1.0, 0.25), Color::rgb(0, 128, 0));
+        assert_eq!(Color::hsl(-120.0, 1.0, 0.5), Color::rgb(0, 0, 255));
+        assert_eq!(Color::hsl(200.0, 0.0, 1.0), Color::rgb(255, 255, 255));
+        assert_eq!(Color::hsv(60.0, 1.0, 1.0), Color::rgb(255, 255, 0));
+        assert_eq!(Color::hsv(300.0, 0.5, 1.0), Color::rgb(255, 128, 255));
+        assert_eq!(Color::hsv(540.0, 1.0, 0.5), Color::rgb(0, 128, 128));
+
+        assert_components(
+            Color::rgb(255, 128, 0).to_hsv(),
+            (60.0 * 128.0 / 255.0, 1.0, 1.0),
+        );
+        assert_components(Color::rgb(0, 0, 255).to_hsl(), (240.0, 1.0, 0.5));
+        assert_components(Color::rgb(191, 64, 191).to_hsl(), (300.0, 0.498, 0.5));
+        assert_components(Color::rgb(0, 0, 0).to_hsv(), (0.0, 0.0, 0.0));
+        assert_components(Color::rgb(255, 255, 255).to_hsl(), (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn hsl_and_hsv_round_trip() {
+        for red in (0..=255).step_by(15) {
+            for green in (0..=255).step_by(15) {
+                for blue in (0..=255).step_by(15) {
+                    let color = Color::rgb(red, green, blue);
+                    let (hue, saturation, lightness) = color.to_hsl();
+                    assert_eq!(Color::hsl(hue, saturation, lightness), color);
+                    let (hue, saturation, value) = color.to_hsv();
+                    assert_eq!(Color::hsv(hue, saturation, value), color);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hsl_adjustments() {
+        let red = Color::rgba(255, 0, 0, 100);
+        assert_eq!(red.rotate_hue(120.0), Color::rgba(0, 255, 0, 100));
+        assert_eq!(red.rotate_hue(-480.0), Color::rgba(0, 0, 255, 100));
+        assert_eq!(red.desaturate(1.0), Color::rgba(128, 128, 128, 100));
+        assert_eq!(Color::rgb(191, 64, 64).saturate(1.0), Color::rgb(255, 0, 0));
+        assert_eq!(red.lighten(0.25), Color::rgba(255, 128, 128, 100));
+        assert_eq!(red.darken(1.0), Color::rgba(0, 0, 0, 100));
+    }
+
+    #[test]
+    fn color_modes_scale_their_ranges() {
+        let rgb = ColorMode::Rgb {
+            ranges: [1.0, 1.0, 1.0, 1.0],
+        };
+        assert_eq!(rgb.color(1.0, 0.5, 0.0, 0.5), Color::rgba(255, 128, 0, 128));
+        assert_eq!(rgb.color(2.0, -1.0, 0.0, 1.0), Color::rgb(255, 0, 0));
+        assert_eq!(
+            ColorMode::default().color(10.0, 20.0, 30.0, 255.0),
+            Color::rgb(10, 20, 30)
+        );
+
+        let hsb = ColorMode::Hsb {
+            ranges: [360.0, 100.0, 100.0, 100.0],
+        };
+        assert_eq!(hsb.color(240.0, 100.0, 50.0, 100.0), Color::rgb(0, 0, 128));
+        let hsl = ColorMode::Hsl {
+            ranges: [1.0, 1.0, 1.0, 1.0],
+        };
+        assert_eq!(
+            hsl.color(1.0 / 3.0, 1.0, 0.5, 0.0),
+            Color::rgba(0, 255, 0, 0)
+        );
+        let empty = ColorMode::Rgb { ranges: [0.0; 4] };
+        assert_eq!(empty.color(1.0, 1.0, 1.0, 1.0), Color::rgba(0, 0, 0, 0));
+    }
 }
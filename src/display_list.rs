@@ -0,0 +1,766 @@
+use crate::color::{BlendMode, Color};
+use crate::font::{Font, FontFace, FontWeight};
+use crate::image::{Image, ImageSampling};
+use crate::path::{FillRule, Path, PathSegment};
+use crate::primitives::{FloatPoint, FloatRect};
+use crate::stroke::{LineCap, LineJoin};
+use crate::transform::Transform;
+use std::fmt::{self, Write};
+use std::sync::Arc;
+
+/// What drawing calls do while the canvas records a display list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recording {
+    /// Shapes are only recorded, the pixels of the canvas stay untouched.
+    VectorOnly,
+    /// Shapes are recorded and rasterized as usual.
+    RasterAndVector,
+}
+
+/// Fill, stroke, blending and transform in effect when a shape was drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapeStyle {
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+    pub stroke_weight: f32,
+    pub stroke_cap: LineCap,
+    pub stroke_join: LineJoin,
+    pub fill_rule: FillRule,
+    pub blend_mode: BlendMode,
+    /// Only affects how the canvas flattens curves, vector output stays exact.
+    pub curve_tolerance: f32,
+    pub matrix: Transform,
+}
+
+/// One line of text, `position` is the start of its baseline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextRun {
+    pub position: FloatPoint,
+    pub text: String,
+    /// Advance of the whole line as laid out on the canvas.
+    pub width: f32,
+}
+
+/// A drawing call in user space, before the transform of its style is applied.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Point(FloatPoint),
+    Line {
+        start: FloatPoint,
+        end: FloatPoint,
+    },
+    Rect(FloatRect),
+    Ellipse {
+        center: FloatPoint,
+        radius_x: f32,
+        radius_y: f32,
+    },
+    Path(Path),
+    /// Text is filled with the fill color. Without a user font it is set in
+    /// the bundled Noto Sans Mono, `size` is the one set with `font_size`.
+    Text {
+        runs: Vec<TextRun>,
+        font: Option<Font>,
+        size: usize,
+        weight: FontWeight,
+    },
+    /// The drawn region of an image with the tint already applied, scaled
+    /// into `dest`.
+    Image {
+        image: Arc<Image>,
+        dest: FloatRect,
+        sampling: ImageSampling,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedShape {
+    pub shape: Shape,
+    pub style: ShapeStyle,
+}
+
+impl RecordedShape {
+    /// A filled path with open sub-paths as a fill of its closed sub-paths
+    /// and a stroke of the whole path, the way the canvas draws it.
+    pub(crate) fn split_open_path(&self) -> Option<Vec<RecordedShape>> {
+        let path = match &self.shape {
+            Shape::Path(path) if self.style.fill.is_some() && !path.is_closed() => path,
+            _ => return None,
+        };
+        let mut parts = Vec::with_capacity(2);
+        let closed = path.closed_subpaths();
+        if closed.segment_count() > 0 {
+            parts.push(RecordedShape {
+                shape: Shape::Path(closed),
+                style: ShapeStyle {
+                    stroke: None,
+                    ..self.style
+                },
+            });
+        }
+        parts.push(RecordedShape {
+            shape: self.shape.clone(),
+            style: ShapeStyle {
+                fill: None,
+                ..self.style
+            },
+        });
+        Some(parts)
+    }
+}
+
+/// A state change or drawing call, the display list expressed as the calls a
+/// sketch would make to draw it again.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Clear(Color),
+    Fill(Option<Color>),
+    Stroke(Option<Color>),
+    StrokeWeight(f32),
+    StrokeCap(LineCap),
+    StrokeJoin(LineJoin),
+    FillRule(FillRule),
+    BlendMode(BlendMode),
+    CurveTolerance(f32),
+    /// Replaces the current transform.
+    Matrix(Transform),
+    Font {
+        font: Option<Font>,
+        size: usize,
+        weight: FontWeight,
+    },
+    Draw(Shape),
+}
+
+/// Difference between two display lists, shapes are compared by position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Resized,
+    Background,
+    Added(usize),
+    Removed(usize),
+    Changed(usize),
+}
+
+/// Shapes recorded by a canvas since it was last cleared.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisplayList {
+    width: usize,
+    height: usize,
+    background: Option<Color>,
+    shapes: Vec<RecordedShape>,
+}
+
+impl DisplayList {
+    pub fn new(width: usize, height: usize) -> Self {
+        DisplayList {
+            width,
+            height,
+            background: None,
+            shapes: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The color the canvas was last cleared with.
+    pub fn background(&self) -> Option<Color> {
+        self.background
+    }
+
+    pub fn shapes(&self) -> &[RecordedShape] {
+        &self.shapes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    pub fn push(&mut self, shape: Shape, style: ShapeStyle) {
+        self.shapes.push(RecordedShape { shape, style });
+    }
+
+    /// Drops all shapes, everything drawn before is covered by `background`.
+    pub fn clear(&mut self, background: Color) {
+        self.shapes.clear();
+        self.background = Some(background);
+    }
+
+    /// The recorded shapes as a sequence of commands, each shape preceded by
+    /// the state changes since the shape before it.
+    pub fn commands(&self) -> Vec<Command> {
+        let mut commands = Vec::new();
+        if let Some(background) = self.background {
+            commands.push(Command::Clear(background));
+        }
+
+        let mut current: Option<ShapeStyle> = None;
+        let mut current_font = None;
+        for recorded in &self.shapes {
+            let style = recorded.style;
+            let changed = |field: fn(&ShapeStyle) -> Command| {
+                let command = field(&style);
+                match current {
+                    Some(ref current) if field(current) == command => None,
+                    _ => Some(command),
+                }
+            };
+            commands.extend(
+                [
+                    changed(|style| Command::Fill(style.fill)),
+                    changed(|style| Command::Stroke(style.stroke)),
+                    changed(|style| Command::StrokeWeight(style.stroke_weight)),
+                    changed(|style| Command::StrokeCap(style.stroke_cap)),
+                    changed(|style| Command::StrokeJoin(style.stroke_join)),
+                    changed(|style| Command::FillRule(style.fill_rule)),
+                    changed(|style| Command::BlendMode(style.blend_mode)),
+                    changed(|style| Command::CurveTolerance(style.curve_tolerance)),
+                    changed(|style| Command::Matrix(style.matrix)),
+                ]
+                .into_iter()
+                .flatten(),
+            );
+            current = Some(style);
+
+            if let Shape::Text {
+                font, size, weight, ..
+            } = &recorded.shape
+            {
+                let font = Command::Font {
+                    font: font.clone(),
+                    size: *size,
+                    weight: *weight,
+                };
+                if current_font.as_ref() != Some(&font) {
+                    commands.push(font.clone());
+                    current_font = Some(font);
+                }
+            }
+            commands.push(Command::Draw(recorded.shape.clone()));
+        }
+        commands
+    }
+
+    /// What changed since `previous`, e.g. to only redraw frames that differ.
+    pub fn changes(&self, previous: &DisplayList) -> Vec<Change> {
+        let mut changes = Vec::new();
+        if (self.width, self.height) != (previous.width, previous.height) {
+            changes.push(Change::Resized);
+        }
+        if self.background != previous.background {
+            changes.push(Change::Background);
+        }
+        for index in 0..self.shapes.len().max(previous.shapes.len()) {
+            match (self.shapes.get(index), previous.shapes.get(index)) {
+                (Some(shape), Some(previous)) if shape != previous => {
+                    changes.push(Change::Changed(index))
+                }
+                (Some(_), None) => changes.push(Change::Added(index)),
+                (None, Some(_)) => changes.push(Change::Removed(index)),
+                _ => {}
+            }
+        }
+        changes
+    }
+
+    /// Standalone SVG document of the same size as the canvas.
+    pub fn to_svg(&self) -> String {
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = self.width,
+            h = self.height
+        );
+        if let Some(background) = self.background {
+            let _ = writeln!(
+                svg,
+                r#"<rect width="100%" height="100%"{}/>"#,
+                paint("fill", Some(background))
+            );
+        }
+        // canvas coordinates address pixel centers, SVG ones pixel corners
+        svg.push_str("<g transform=\"translate(0.5 0.5)\">\n");
+        for recorded in &self.shapes {
+            write_svg_shape(&mut svg, recorded);
+        }
+        svg.push_str("</g>\n</svg>\n");
+        svg
+    }
+}
+
+/// One command per line, for inspecting frames while debugging.
+impl fmt::Display for DisplayList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "DisplayList {}x{}", self.width, self.height)?;
+        for command in self.commands() {
+            writeln!(f, "  {:?}", command)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shortest decimal representation, rounded to a thousandth of a pixel.
+pub(crate) fn number(value: f32) -> String {
+    ((value * 1000.0).round() / 1000.0 + 0.0).to_string()
+}
+
+fn paint(attribute: &str, color: Option<Color>) -> String {
+    match color {
+        None => format!(r#" {}="none""#, attribute),
+        Some(color) if color.alpha == 255 => format!(
+            r##" {}="#{:02x}{:02x}{:02x}""##,
+            attribute, color.red, color.green, color.blue
+        ),
+        Some(color) => format!(
+            r##" {a}="#{:02x}{:02x}{:02x}" {a}-opacity="{}""##,
+            color.red,
+            color.green,
+            color.blue,
+            number(color.alpha as f32 / 255.0),
+            a = attribute
+        ),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * index) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// SVG path data of `path`, arcs keep their exact form.
+fn svg_path_data(path: &Path) -> String {
+    let mut data = String::new();
+    // SVG requires a current point, paths start at the origin like `polylines`
+    if !matches!(path.segments().first(), None | Some(PathSegment::MoveTo(_))) {
+        data.push_str("M0 0");
+    }
+    for segment in path.segments() {
+        if !data.is_empty() {
+            data.push(' ');
+        }
+        let _ = match segment {
+            PathSegment::MoveTo(pt) => write!(data, "M{} {}", number(pt.x), number(pt.y)),
+            PathSegment::LineTo(pt) => write!(data, "L{} {}", number(pt.x), number(pt.y)),
+            PathSegment::QuadTo(control, pt) => write!(
+                data,
+                "Q{} {} {} {}",
+                number(control.x),
+                number(control.y),
+                number(pt.x),
+                number(pt.y)
+            ),
+            PathSegment::CubicTo(control1, control2, pt) => write!(
+                data,
+                "C{} {} {} {} {} {}",
+                number(control1.x),
+                number(control1.y),
+                number(control2.x),
+                number(control2.y),
+                number(pt.x),
+                number(pt.y)
+            ),
+            PathSegment::ArcTo {
+                radius_x,
+                radius_y,
+                rotation,
+                large_arc,
+                sweep,
+                end,
+            } => write!(
+                data,
+                "A{} {} {} {} {} {} {}",
+                number(*radius_x),
+                number(*radius_y),
+                number(rotation.to_degrees()),
+                *large_arc as u8,
+                *sweep as u8,
+                number(end.x),
+                number(end.y)
+            ),
+            PathSegment::Close => write!(data, "Z"),
+        };
+    }
+    data
+}
+
+/// CSS blend mode matching `mode`, `None` for plain source over blending.
+fn svg_blend_mode(mode: BlendMode) -> Option<&'static str> {
+    match mode {
+        BlendMode::SourceOver => None,
+        BlendMode::Add => Some("plus-lighter"),
+        BlendMode::Multiply => Some("multiply"),
+        BlendMode::Screen => Some("screen"),
+        BlendMode::Lighten => Some("lighten"),
+        BlendMode::Darken => Some("darken"),
+        BlendMode::Difference => Some("difference"),
+    }
+}
+
+fn write_svg_shape(svg: &mut String, recorded: &RecordedShape) {
+    if let Some(parts) = recorded.split_open_path() {
+        for part in &parts {
+            write_svg_shape(svg, part);
+        }
+        return;
+    }
+    let style = &recorded.style;
+    let blend = match svg_blend_mode(style.blend_mode) {
+        Some(mode) => format!(r#" style="mix-blend-mode:{}""#, mode),
+        None => String::new(),
+    };
+    let mut attributes = blend.clone();
+    let matrix = style.matrix;
+    if !matrix.is_identity() {
+        let _ = write!(
+            attributes,
+            r#" transform="matrix({} {} {} {} {} {})""#,
+            number(matrix.a),
+            number(matrix.b),
+            number(matrix.c),
+            number(matrix.d),
+            number(matrix.e),
+            number(matrix.f)
+        );
+    }
+
+    // images cover pixels from corner to corner, unlike the other shapes
+    if let Shape::Image {
+        image,
+        dest,
+        sampling,
+    } = &recorded.shape
+    {
+        let rendering = match sampling {
+            ImageSampling::Nearest => "pixelated",
+            ImageSampling::Bilinear => "optimizeQuality",
+        };
+        if let Ok(png) = image.encode_png() {
+            let _ = writeln!(
+                svg,
+                r#"<g transform="translate(-0.5 -0.5)"><image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" image-rendering="{}"{} href="data:image/png;base64,{}"/></g>"#,
+                number(dest.x()),
+                number(dest.y()),
+                number(dest.width),
+                number(dest.height),
+                rendering,
+                attributes,
+                base64(&png)
+            );
+        }
+        return;
+    }
+
+    if let Shape::Text {
+        runs,
+        font,
+        size,
+        weight,
+    } = &recorded.shape
+    {
+        if style.fill.is_none() {
+            return;
+        }
+        let family = match font {
+            Some(font) => format!(
+                "{}, sans-serif",
+                escape(font.name().unwrap_or("sans-serif"))
+            ),
+            None => String::from("Noto Sans Mono, monospace"),
+        };
+        let em_size = FontFace::new(font.as_ref(), *size, *weight).em_size();
+        let weight = match weight {
+            FontWeight::Light => 300,
+            FontWeight::Regular => 400,
+            FontWeight::Bold => 700,
+        };
+        for run in runs {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-family="{}" font-size="{}" font-weight="{}" textLength="{}" lengthAdjust="spacingAndGlyphs" xml:space="preserve"{}{}>{}</text>"#,
+                number(run.position.x),
+                number(run.position.y),
+                family,
+                number(em_size),
+                weight,
+                number(run.width),
+                paint("fill", style.fill),
+                attributes,
+                escape(&run.text)
+            );
+        }
+        return;
+    }
+
+    // points cover exactly one device pixel, whatever the transform
+    if let Shape::Point(pt) = &recorded.shape {
+        let pt = matrix.apply(pt);
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="1" height="1"{}{}/>"#,
+            number(pt.x - 0.5),
+            number(pt.y - 0.5),
+            paint("fill", style.stroke),
+            blend
+        );
+        return;
+    }
+
+    let fill = match recorded.shape {
+        Shape::Line { .. } => None,
+        _ => style.fill,
+    };
+    attributes.push_str(&paint("fill", fill));
+    if fill.is_some() && style.fill_rule == FillRule::EvenOdd {
+        attributes.push_str(r#" fill-rule="evenodd""#);
+    }
+    attributes.push_str(&paint("stroke", style.stroke));
+    if style.stroke.is_some() {
+        // thin strokes are drawn as one pixel hairlines whatever the transform
+        if style.stroke_weight * matrix.scale_factor() > 1.0 {
+            let _ = write!(
+                attributes,
+                r#" stroke-width="{}""#,
+                number(style.stroke_weight)
+            );
+        } else {
+            attributes.push_str(r#" stroke-width="1" vector-effect="non-scaling-stroke""#);
+        }
+        let cap = match style.stroke_cap {
+            LineCap::Butt => "butt",
+            LineCap::Round => "round",
+            LineCap::Square => "square",
+        };
+        let join = match style.stroke_join {
+            LineJoin::Miter => "miter",
+            LineJoin::Round => "round",
+            LineJoin::Bevel => "bevel",
+        };
+        let _ = write!(
+            attributes,
+            r#" stroke-linecap="{}" stroke-linejoin="{}""#,
+            cap, join
+        );
+    }
+
+    let _ = match &recorded.shape {
+        Shape::Line { start, end } => writeln!(
+            svg,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}"{}/>"#,
+            number(start.x),
+            number(start.y),
+            number(end.x),
+            number(end.y),
+            attributes
+        ),
+        Shape::Rect(rect) => writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}"{}/>"#,
+            number(rect.x()),
+            number(rect.y()),
+            number(rect.width),
+            number(rect.height),
+            attributes
+        ),
+        Shape::Ellipse {
+            center,
+            radius_x,
+            radius_y,
+        } => writeln!(
+            svg,
+            r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}"{}/>"#,
+            number(center.x),
+            number(center.y),
+            number(*radius_x),
+            number(*radius_y),
+            attributes
+        ),
+        Shape::Path(path) => writeln!(svg, r#"<path d="{}"{}/>"#, svg_path_data(path), attributes),
+        Shape::Point(_) | Shape::Text { .. } | Shape::Image { .. } => Ok(()),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::Canvas;
+
+    fn recording_canvas() -> Canvas {
+        let mut canvas = Canvas::new(20, 20);
+        canvas.begin_recording(Recording::VectorOnly);
+        canvas
+    }
+
+    #[test]
+    fn open_sub_paths_are_not_filled_in_svg() {
+        let mut canvas = recording_canvas();
+        canvas.fill(Color::rgb(255, 0, 0));
+        let mut path = Path::new();
+        path.move_to((0.0, 0.0));
+        path.line_to((10.0, 0.0));
+        path.line_to((0.0, 10.0));
+        path.close();
+        path.move_to((15.0, 15.0));
+        path.line_to((19.0, 15.0));
+        path.line_to((15.0, 19.0));
+        canvas.draw_path(&path);
+
+        let svg = canvas.display_list().unwrap().to_svg();
+        assert!(svg.contains(r##"<path d="M0 0 L10 0 L0 10 Z" fill="#ff0000" stroke="none"/>"##));
+        assert!(svg.contains(
+            r##"<path d="M0 0 L10 0 L0 10 Z M15 15 L19 15 L15 19" fill="none" stroke="#000000""##
+        ));
+    }
+
+    #[test]
+    fn svg_paths_without_move_to_start_at_origin() {
+        let mut canvas = recording_canvas();
+        let mut path = Path::new();
+        path.line_to((5.0, 5.0));
+        path.line_to((10.0, 0.0));
+        canvas.draw_path(&path);
+
+        let svg = canvas.display_list().unwrap().to_svg();
+        assert!(svg.contains(r#"<path d="M0 0 L5 5 L10 0" fill="none""#));
+    }
+
+    #[test]
+    fn images_are_recorded() {
+        let mut image = Image::new(2, 2);
+        image.set_pixel(1, 1, Color::rgb(0, 0, 255));
+        let mut canvas = recording_canvas();
+        canvas.tint(Color::rgba(255, 255, 255, 128));
+        canvas.draw_image(
+            &image,
+            FloatRect::new(FloatPoint::new(2.0, 3.0), 4.0, 5.0),
+            Some(crate::canvas::IntRect::new(Default::default(), 2, 1)),
+        );
+        assert_eq!(canvas.as_raw_buffer()[..4], [255, 255, 255, 255]);
+
+        let display_list = canvas.display_list().unwrap();
+        let mut expected = Image::new(2, 1);
+        expected.set_pixel(0, 0, Color::rgba(255, 255, 255, 128));
+        expected.set_pixel(1, 0, Color::rgba(255, 255, 255, 128));
+        assert_eq!(
+            display_list.shapes()[0].shape,
+            Shape::Image {
+                image: Arc::new(expected),
+                dest: FloatRect::new(FloatPoint::new(2.0, 3.0), 4.0, 5.0),
+                sampling: ImageSampling::Nearest,
+            }
+        );
+        assert!(display_list.to_svg().contains(
+            r#"<image x="2" y="3" width="4" height="5" preserveAspectRatio="none" image-rendering="pixelated" href="data:image/png;base64,iVBORw0KGgo"#
+        ));
+    }
+
+    #[test]
+    fn blend_modes_are_recorded() {
+        let mut canvas = recording_canvas();
+        canvas.fill(Color::rgb(255, 0, 0));
+        canvas.draw_rect(FloatRect::new(FloatPoint::new(0.0, 0.0), 4.0, 4.0));
+        canvas.blend_mode(BlendMode::Multiply);
+        canvas.draw_rect(FloatRect::new(FloatPoint::new(2.0, 2.0), 4.0, 4.0));
+
+        let display_list = canvas.display_list().unwrap();
+        let blend_modes: Vec<Command> = display_list
+            .commands()
+            .into_iter()
+            .filter(|command| matches!(command, Command::BlendMode(_)))
+            .collect();
+        assert_eq!(
+            blend_modes,
+            [
+                Command::BlendMode(BlendMode::SourceOver),
+                Command::BlendMode(BlendMode::Multiply)
+            ]
+        );
+        let svg = display_list.to_svg();
+        assert!(svg.contains(r##"<rect x="0" y="0" width="4" height="4" fill="#ff0000""##));
+        assert!(svg.contains(
+            r##"<rect x="2" y="2" width="4" height="4" style="mix-blend-mode:multiply" fill="#ff0000""##
+        ));
+    }
+
+    #[test]
+    fn base64_pads_the_last_group() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(&[0xff, 0xfe]), "//4=");
+    }
+
+    fn rects(count: usize, fill: Color) -> DisplayList {
+        let mut canvas = recording_canvas();
+        canvas.fill(fill);
+        for index in 0..count {
+            canvas.draw_rect(FloatRect::new(FloatPoint::new(index as f32, 0.0), 2.0, 2.0));
+        }
+        canvas.display_list().unwrap().clone()
+    }
+
+    #[test]
+    fn changes_compare_shapes_by_position() {
+        let red = Color::rgb(255, 0, 0);
+        assert_eq!(rects(2, red).changes(&rects(2, red)), []);
+        assert_eq!(
+            rects(3, red).changes(&rects(1, red)),
+            [Change::Added(1), Change::Added(2)]
+        );
+        assert_eq!(rects(1, red).changes(&rects(2, red)), [Change::Removed(1)]);
+
+        let mut modified = recording_canvas();
+        modified.fill(red);
+        modified.draw_rect(FloatRect::new(FloatPoint::new(0.0, 0.0), 2.0, 2.0));
+        modified.fill(Color::rgb(0, 0, 255));
+        modified.draw_rect(FloatRect::new(FloatPoint::new(1.0, 0.0), 2.0, 2.0));
+        assert_eq!(
+            modified.display_list().unwrap().changes(&rects(2, red)),
+            [Change::Changed(1)]
+        );
+    }
+
+    #[test]
+    fn changes_of_size_and_background() {
+        let mut canvas = Canvas::new(30, 20);
+        canvas.begin_recording(Recording::VectorOnly);
+        canvas.set_background(Color::rgb(0, 0, 0));
+        canvas.clear();
+        let red = Color::rgb(255, 0, 0);
+        assert_eq!(
+            canvas.display_list().unwrap().changes(&rects(0, red)),
+            [Change::Resized, Change::Background]
+        );
+    }
+}
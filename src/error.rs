@@ -0,0 +1,105 @@
+use crate::color::HexColorError;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Malformed textual input, `position` is the byte offset the problem was found at.
+    Parse {
+        message: String,
+        position: usize,
+    },
+    /// Binary data such as an image or font that could not be decoded.
+    Decode(String),
+    Io(io::Error),
+    /// A rendering backend failed to initialize or to present a frame.
+    Backend(String),
+}
+
+impl Error {
+    pub(crate) fn parse(message: &str, position: usize) -> Self {
+        Error::Parse {
+            message: message.to_string(),
+            position,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse { message, position } => {
+                write!(f, "parse error at position {}: {}", position, message)
+            }
+            Error::Decode(message) => write!(f, "decode error: {}", message),
+            Error::Io(error) => write!(f, "i/o error: {}", error),
+            Error::Backend(message) => write!(f, "backend error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<HexColorError> for Error {
+    fn from(error: HexColorError) -> Self {
+        match error {
+            HexColorError::MissingHash => Error::parse("hex color has to start with '#'", 0),
+            HexColorError::InvalidLength(length) => {
+                Error::parse("hex color needs 3, 4, 6 or 8 digits", length)
+            }
+            HexColorError::InvalidDigit { position } => Error::parse("invalid hex digit", position),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::image::Image;
+
+    fn position(error: Error) -> usize {
+        match error {
+            Error::Parse { position, .. } => position,
+            error => panic!("expected a parse error, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn hex_color_errors_keep_their_position() {
+        assert_eq!(position(Color::hex("ff0000").unwrap_err().into()), 0);
+        assert_eq!(position(Color::hex("#ff000").unwrap_err().into()), 6);
+        assert_eq!(position(Color::hex("#ff0z00").unwrap_err().into()), 4);
+    }
+
+    #[test]
+    fn pnm_errors_point_at_the_offending_token() {
+        assert_eq!(position(Image::decode(b"P7 1 1 255\n").unwrap_err()), 0);
+        assert_eq!(position(Image::decode(b"P2 2 x 255\n").unwrap_err()), 5);
+        assert_eq!(position(Image::decode(b"P2 2 1 0\n").unwrap_err()), 8);
+        assert_eq!(position(Image::decode(b"P2 # comment\n2").unwrap_err()), 14);
+        assert_eq!(position(Image::decode(b"P5 2 2 255\n\0").unwrap_err()), 12);
+    }
+
+    #[test]
+    fn display_includes_the_position() {
+        let error: Error = Color::hex("#12g").unwrap_err().into();
+        assert_eq!(
+            error.to_string(),
+            "parse error at position 3: invalid hex digit"
+        );
+    }
+}
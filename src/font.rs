@@ -0,0 +1,335 @@
+use crate::error::Error;
+use noto_sans_mono_bitmap::{get_bitmap, get_bitmap_width, BitmapHeight};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FontWeight {
+    Light,
+    Regular,
+    Bold,
+}
+
+/// Horizontal placement of text relative to the anchor point or box.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// Vertical placement of text relative to the anchor point or box.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerticalAlign {
+    Top,
+    /// The baseline of the first line sits on the anchor point.
+    Baseline,
+    Middle,
+    Bottom,
+}
+
+/// TrueType or OpenType font, cloning is cheap as the parsed font is shared.
+#[derive(Clone)]
+pub struct Font {
+    inner: Arc<fontdue::Font>,
+    data: Arc<[u8]>,
+}
+
+impl Font {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let font = fontdue::Font::from_bytes(data, fontdue::FontSettings::default())
+            .map_err(|error| Error::Decode(error.to_string()))?;
+        Ok(Font {
+            inner: Arc::new(font),
+            data: Arc::from(data),
+        })
+    }
+
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Font::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.inner.name()
+    }
+
+    /// The font file as it was loaded.
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub(crate) fn glyph_index(&self, char: char) -> u16 {
+        self.inner.lookup_glyph_index(char)
+    }
+
+    /// Advance and outline bounds `(xmin, ymin, xmax, ymax)` of a glyph, in
+    /// thousandths of an em.
+    pub(crate) fn glyph_metrics(&self, index: u16) -> (f32, [f32; 4]) {
+        let metrics = self.inner.metrics_indexed(index, 1000.0);
+        let bounds = metrics.bounds;
+        (
+            metrics.advance_width,
+            [
+                bounds.xmin,
+                bounds.ymin,
+                bounds.xmin + bounds.width,
+                bounds.ymin + bounds.height,
+            ],
+        )
+    }
+
+    /// Kerning between two glyphs in thousandths of an em.
+    pub(crate) fn kerning(&self, left: char, right: char) -> f32 {
+        self.inner
+            .horizontal_kern(left, right, 1000.0)
+            .unwrap_or(0.0)
+    }
+
+    /// Ascent and descent in thousandths of an em, the descent is negative.
+    pub(crate) fn line_metrics(&self) -> (f32, f32) {
+        self.inner
+            .horizontal_line_metrics(1000.0)
+            .map_or((1000.0, 0.0), |metrics| (metrics.ascent, metrics.descent))
+    }
+}
+
+/// Fonts are equal when they are clones sharing the same parsed font.
+impl PartialEq for Font {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Font").field(&self.name()).finish()
+    }
+}
+
+/// The font text is currently rendered with, either the bundled bitmap font
+/// or a user supplied one at an arbitrary pixel size.
+pub(crate) enum FontFace<'a> {
+    Bitmap {
+        weight: noto_sans_mono_bitmap::FontWeight,
+        height: BitmapHeight,
+    },
+    TrueType {
+        font: &'a Font,
+        size: f32,
+    },
+}
+
+impl<'a> FontFace<'a> {
+    pub(crate) fn new(font: Option<&'a Font>, size: usize, weight: FontWeight) -> Self {
+        if let Some(font) = font {
+            return FontFace::TrueType {
+                font,
+                size: size as f32,
+            };
+        }
+
+        let weight = match weight {
+            FontWeight::Light => noto_sans_mono_bitmap::FontWeight::Light,
+            FontWeight::Regular => noto_sans_mono_bitmap::FontWeight::Regular,
+            FontWeight::Bold => noto_sans_mono_bitmap::FontWeight::Bold,
+        };
+        let height = match size {
+            0..=14 => BitmapHeight::Size14,
+            15..=16 => BitmapHeight::Size16,
+            17..=18 => BitmapHeight::Size18,
+            19..=20 => BitmapHeight::Size20,
+            21..=22 => BitmapHeight::Size22,
+            23..=24 => BitmapHeight::Size24,
+            25..=32 => BitmapHeight::Size32,
+            _ => BitmapHeight::Size64,
+        };
+        FontFace::Bitmap { weight, height }
+    }
+
+    /// Distance from the top of a line to the top of the next one.
+    pub(crate) fn line_height(&self) -> f32 {
+        match self {
+            FontFace::Bitmap { height, .. } => *height as usize as f32,
+            FontFace::TrueType { font, size } => font
+                .inner
+                .horizontal_line_metrics(*size)
+                .map(|metrics| metrics.new_line_size)
+                .unwrap_or(*size),
+        }
+    }
+
+    /// Distance from the top of a line to its baseline.
+    pub(crate) fn ascent(&self) -> f32 {
+        match self {
+            // the bundled glyphs place the baseline at roughly 4/5 of the height
+            FontFace::Bitmap { height, .. } => (*height as usize as f32 * 0.8).round(),
+            FontFace::TrueType { font, size } => font
+                .inner
+                .horizontal_line_metrics(*size)
+                .map(|metrics| metrics.ascent)
+                .unwrap_or(*size),
+        }
+    }
+
+    /// Size of the em square, for the bitmap font derived from the advance
+    /// of Noto Sans Mono which is 0.6 em.
+    pub(crate) fn em_size(&self) -> f32 {
+        match self {
+            FontFace::Bitmap { weight, height } => get_bitmap_width(*weight, *height) as f32 / 0.6,
+            FontFace::TrueType { size, .. } => *size,
+        }
+    }
+
+    /// Horizontal advance of a single line of text.
+    pub(crate) fn line_width(&self, line: &str) -> f32 {
+        match self {
+            FontFace::Bitmap { weight, height } => {
+                (line.chars().count() * get_bitmap_width(*weight, *height)) as f32
+            }
+            FontFace::TrueType { font, size } => {
+                let mut width = 0.0;
+                let mut previous = None;
+                for char in line.chars() {
+                    if let Some(previous) = previous {
+                        width += font
+                            .inner
+                            .horizontal_kern(previous, char, *size)
+                            .unwrap_or(0.0);
+                    }
+                    width += font.inner.metrics(char, *size).advance_width;
+                    previous = Some(char);
+                }
+                width
+            }
+        }
+    }
+
+    /// Greedily breaks every paragraph into lines no wider than `width`,
+    /// words longer than `width` are split between characters.
+    pub(crate) fn wrap(&self, text: &str, width: f32) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                let candidate = if line.is_empty() {
+                    word.to_string()
+                } else {
+                    format!("{} {}", line, word)
+                };
+                if self.line_width(&candidate) <= width {
+                    line = candidate;
+                    continue;
+                }
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                for char in word.chars() {
+                    line.push(char);
+                    if self.line_width(&line) > width && line.chars().count() > 1 {
+                        line.pop();
+                        lines.push(std::mem::take(&mut line));
+                        line.push(char);
+                    }
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Writes the coverage of `line` into `mask`, the top of the line being
+    /// placed at `(x, y)`. Overlapping glyphs keep the stronger coverage.
+    pub(crate) fn render_line(
+        &self,
+        line: &str,
+        mask: &mut [u8],
+        mask_width: usize,
+        x: f32,
+        y: f32,
+    ) {
+        let mask_height = mask.len() / mask_width.max(1);
+        let mut plot = |px: isize, py: isize, intensity: u8| {
+            if px >= 0 && py >= 0 && (px as usize) < mask_width && (py as usize) < mask_height {
+                let index = py as usize * mask_width + px as usize;
+                mask[index] = mask[index].max(intensity);
+            }
+        };
+
+        match self {
+            FontFace::Bitmap { weight, height } => {
+                let char_width = get_bitmap_width(*weight, *height);
+                let (x, y) = (x.round() as isize, y.round() as isize);
+                for (char_i, char) in line.chars().enumerate() {
+                    let bitmap_char = get_bitmap(char, *weight, *height).unwrap_or(
+                        //Fall back to whitespace for unknown char
+                        get_bitmap(' ', *weight, *height).unwrap(),
+                    );
+                    for (row_i, row) in bitmap_char.bitmap().iter().enumerate() {
+                        for (col_i, intensity) in row.iter().enumerate() {
+                            let px = x + (char_i * char_width + col_i) as isize;
+                            plot(px, y + row_i as isize, *intensity);
+                        }
+                    }
+                }
+            }
+            FontFace::TrueType { font, size } => {
+                let baseline = (y + self.ascent()).round() as isize;
+                let mut pen = x;
+                let mut previous = None;
+                for char in line.chars() {
+                    if let Some(previous) = previous {
+                        pen += font
+                            .inner
+                            .horizontal_kern(previous, char, *size)
+                            .unwrap_or(0.0);
+                    }
+                    let (metrics, bitmap) = font.inner.rasterize(char, *size);
+                    let left = pen.round() as isize + metrics.xmin as isize;
+                    let top = baseline - metrics.ymin as isize - metrics.height as isize;
+                    for (index, intensity) in bitmap.iter().enumerate() {
+                        let col = (index % metrics.width.max(1)) as isize;
+                        let row = (index / metrics.width.max(1)) as isize;
+                        plot(left + col, top + row, *intensity);
+                    }
+                    pen += metrics.advance_width;
+                    previous = Some(char);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap(text: &str, chars: f32) -> Vec<String> {
+        let face = FontFace::new(None, 14, FontWeight::Regular);
+        face.wrap(text, face.line_width("x") * chars)
+    }
+
+    #[test]
+    fn wraps_between_words() {
+        assert_eq!(wrap("one two three", 7.0), ["one two", "three"]);
+        assert_eq!(wrap("one   two", 7.0), ["one two"]);
+    }
+
+    #[test]
+    fn splits_long_words() {
+        assert_eq!(
+            wrap("hi abcdefgh yo", 3.0),
+            ["hi", "abc", "def", "gh", "yo"]
+        );
+        // a line keeps at least one character however narrow
+        assert_eq!(wrap("ab", 0.5), ["a", "b"]);
+    }
+
+    #[test]
+    fn keeps_newlines_and_empty_text() {
+        assert_eq!(wrap("a\n\nb c", 10.0), ["a", "", "b c"]);
+        assert_eq!(wrap("", 10.0), [""]);
+        assert_eq!(wrap("\n", 10.0), ["", ""]);
+    }
+}
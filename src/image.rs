@@ -0,0 +1,352 @@
+use crate::canvas::{Color, IntRect};
+use crate::error::Error;
+use crate::pixelbuffer::PixelBuffer;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+/// How pixels are looked up when an image is drawn at a different size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageSampling {
+    Nearest,
+    Bilinear,
+}
+
+#[derive(PartialEq)]
+pub struct Image {
+    pixelbuffer: PixelBuffer,
+}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Image")
+            .field("width", &self.width())
+            .field("height", &self.height())
+            .finish()
+    }
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self {
+        Image {
+            pixelbuffer: PixelBuffer::new(width, height),
+        }
+    }
+
+    pub fn from_pixelbuffer(pixelbuffer: PixelBuffer) -> Self {
+        Image { pixelbuffer }
+    }
+
+    /// Loads a PNG, PPM or PGM file, the format is detected from the file contents.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut data = Vec::new();
+        BufReader::new(File::open(path)?).read_to_end(&mut data)?;
+        Image::decode(&data)
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        if data.starts_with(b"\x89PNG") {
+            Image::decode_png(data)
+        } else if data.starts_with(b"P") {
+            Image::decode_pnm(data)
+        } else {
+            Err(Error::Decode("unknown image format".to_string()))
+        }
+    }
+
+    pub fn decode_png(data: &[u8]) -> Result<Self, Error> {
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder
+            .read_info()
+            .map_err(|error| Error::Decode(error.to_string()))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|error| Error::Decode(error.to_string()))?;
+
+        let channels = info.color_type.samples();
+        let mut image = Image::new(info.width as usize, info.height as usize);
+        for y in 0..info.height as usize {
+            let row = &buffer[y * info.line_size..(y + 1) * info.line_size];
+            for (x, pixel) in row.chunks_exact(channels).enumerate() {
+                let color = match pixel {
+                    [gray] => Color::rgb(*gray, *gray, *gray),
+                    [gray, alpha] => Color::rgba(*gray, *gray, *gray, *alpha),
+                    [red, green, blue] => Color::rgb(*red, *green, *blue),
+                    [red, green, blue, alpha] => Color::rgba(*red, *green, *blue, *alpha),
+                    _ => unreachable!(),
+                };
+                image.set_pixel(x, y, color);
+            }
+        }
+        Ok(image)
+    }
+
+    /// Decodes binary (P5, P6) and plain (P2, P3) PGM and PPM images.
+    pub fn decode_pnm(data: &[u8]) -> Result<Self, Error> {
+        let mut cursor = 0;
+        let magic = next_token(data, &mut cursor)?;
+        let (channels, binary) = match magic {
+            b"P2" => (1, false),
+            b"P3" => (3, false),
+            b"P5" => (1, true),
+            b"P6" => (3, true),
+            _ => return Err(Error::parse("unsupported PNM variant", 0)),
+        };
+        let width = next_number(data, &mut cursor)?;
+        let height = next_number(data, &mut cursor)?;
+        let max_value = next_number(data, &mut cursor)?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(Error::parse("invalid maximum value", cursor));
+        }
+
+        let sample_count = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(channels))
+            .ok_or_else(|| Error::parse("image dimensions too large", cursor))?;
+        let samples: Vec<usize> = if binary {
+            // exactly one whitespace character separates the header from the data
+            cursor += 1;
+            let sample_size = if max_value > 255 { 2 } else { 1 };
+            let end = sample_count
+                .checked_mul(sample_size)
+                .and_then(|size| size.checked_add(cursor))
+                .filter(|end| *end <= data.len())
+                .ok_or_else(|| Error::parse("unexpected end of data", data.len()))?;
+            let raw = &data[cursor..end];
+            raw.chunks_exact(sample_size)
+                .map(|sample| match sample {
+                    [high, low] => (*high as usize) << 8 | *low as usize,
+                    [value] => *value as usize,
+                    _ => unreachable!(),
+                })
+                .collect()
+        } else {
+            // every sample takes at least one digit
+            if sample_count > data.len().saturating_sub(cursor) {
+                return Err(Error::parse("unexpected end of data", data.len()));
+            }
+            (0..sample_count)
+                .map(|_| next_number(data, &mut cursor))
+                .collect::<Result<_, _>>()?
+        };
+
+        let scale = |sample: usize| (sample.min(max_value) * 255 / max_value) as u8;
+        let mut image = Image::new(width, height);
+        for (index, pixel) in samples.chunks_exact(channels).enumerate() {
+            let color = match pixel {
+                [gray] => Color::rgb(scale(*gray), scale(*gray), scale(*gray)),
+                [red, green, blue] => Color::rgb(scale(*red), scale(*green), scale(*blue)),
+                _ => unreachable!(),
+            };
+            image.set_pixel(index % width, index / width, color);
+        }
+        Ok(image)
+    }
+
+    pub fn width(&self) -> usize {
+        self.pixelbuffer.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.pixelbuffer.height()
+    }
+
+    pub fn bounds(&self) -> IntRect {
+        IntRect::new(
+            Default::default(),
+            self.width() as isize,
+            self.height() as isize,
+        )
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Option<&Color> {
+        if x >= self.width() || y >= self.height() {
+            return None;
+        }
+        self.pixelbuffer.at(x, y)
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        self.pixelbuffer.set_pixel(x as isize, y as isize, &color);
+    }
+
+    pub fn pixelbuffer(&self) -> &PixelBuffer {
+        &self.pixelbuffer
+    }
+
+    pub fn pixelbuffer_mut(&mut self) -> &mut PixelBuffer {
+        &mut self.pixelbuffer
+    }
+
+    /// Copy of the `source` region with every pixel multiplied by `tint`.
+    pub(crate) fn cropped(&self, source: &IntRect, tint: Option<Color>) -> Image {
+        let mut image = Image::new(source.width as usize, source.height as usize);
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                let color = self
+                    .pixelbuffer
+                    .at(x + source.x() as usize, y + source.y() as usize);
+                if let Some(color) = color {
+                    image.set_pixel(x, y, tinted(*color, tint));
+                }
+            }
+        }
+        image
+    }
+
+    pub(crate) fn encode_png(&self) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, self.width() as u32, self.height() as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(self.pixelbuffer.as_raw_buffer()))
+            .map_err(|error| Error::Backend(error.to_string()))?;
+        Ok(data)
+    }
+
+    /// Looks up the color at the image position `(x, y)`, reads are clamped
+    /// to the `source` region so scaled edges do not bleed in neighbours.
+    pub(crate) fn sample(
+        &self,
+        x: f32,
+        y: f32,
+        source: &IntRect,
+        sampling: ImageSampling,
+    ) -> Color {
+        let min_x = source.x().max(0);
+        let min_y = source.y().max(0);
+        let max_x = (source.x() + source.width).min(self.width() as isize) - 1;
+        let max_y = (source.y() + source.height).min(self.height() as isize) - 1;
+        let at = |x: isize, y: isize| {
+            *self
+                .pixelbuffer
+                .at(
+                    x.clamp(min_x, max_x) as usize,
+                    y.clamp(min_y, max_y) as usize,
+                )
+                .unwrap()
+        };
+
+        match sampling {
+            ImageSampling::Nearest => at(x.floor() as isize, y.floor() as isize),
+            ImageSampling::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as isize, y0 as isize);
+                let weighted = [
+                    (at(x0, y0), (1.0 - fx) * (1.0 - fy)),
+                    (at(x0 + 1, y0), fx * (1.0 - fy)),
+                    (at(x0, y0 + 1), (1.0 - fx) * fy),
+                    (at(x0 + 1, y0 + 1), fx * fy),
+                ];
+
+                // interpolate premultiplied so transparent pixels do not darken edges
+                let mut sum = [0.0f32; 4];
+                for (color, weight) in weighted {
+                    let alpha = color.alpha as f32 * weight;
+                    sum[0] += color.red as f32 * alpha;
+                    sum[1] += color.green as f32 * alpha;
+                    sum[2] += color.blue as f32 * alpha;
+                    sum[3] += alpha;
+                }
+                if sum[3] <= 0.0 {
+                    return Color::rgba(0, 0, 0, 0);
+                }
+                Color::rgba(
+                    (sum[0] / sum[3]).round() as u8,
+                    (sum[1] / sum[3]).round() as u8,
+                    (sum[2] / sum[3]).round() as u8,
+                    sum[3].round() as u8,
+                )
+            }
+        }
+    }
+}
+
+/// Multiplies every channel of `color` with the one of `tint`.
+pub(crate) fn tinted(color: Color, tint: Option<Color>) -> Color {
+    let tint = match tint {
+        Some(tint) => tint,
+        None => return color,
+    };
+    let multiply = |a: u8, b: u8| ((a as u16 * b as u16 + 127) / 255) as u8;
+    Color::rgba(
+        multiply(color.red, tint.red),
+        multiply(color.green, tint.green),
+        multiply(color.blue, tint.blue),
+        multiply(color.alpha, tint.alpha),
+    )
+}
+
+fn skip_whitespace_and_comments(data: &[u8], cursor: &mut usize) {
+    while *cursor < data.len() {
+        if data[*cursor] == b'#' {
+            while *cursor < data.len() && data[*cursor] != b'\n' {
+                *cursor += 1;
+            }
+        } else if data[*cursor].is_ascii_whitespace() {
+            *cursor += 1;
+        } else {
+            break;
+        }
+    }
+}
+
+fn next_token<'a>(data: &'a [u8], cursor: &mut usize) -> Result<&'a [u8], Error> {
+    skip_whitespace_and_comments(data, cursor);
+    let start = *cursor;
+    while *cursor < data.len() && !data[*cursor].is_ascii_whitespace() {
+        *cursor += 1;
+    }
+    if start == *cursor {
+        return Err(Error::parse("unexpected end of header", start));
+    }
+    Ok(&data[start..*cursor])
+}
+
+fn next_number(data: &[u8], cursor: &mut usize) -> Result<usize, Error> {
+    let token = next_token(data, cursor)?;
+    let position = *cursor - token.len();
+    std::str::from_utf8(token)
+        .ok()
+        .and_then(|token| token.parse().ok())
+        .ok_or_else(|| Error::parse("invalid number", position))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_binary_and_plain_pnm() {
+        let image = Image::decode(b"P6 2 1 255\n\xff\x00\x00\x00\x00\xff").unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.pixel(0, 0), Some(&Color::rgb(255, 0, 0)));
+        assert_eq!(image.pixel(1, 0), Some(&Color::rgb(0, 0, 255)));
+
+        let image = Image::decode(b"P2\n# comment\n2 1\n4\n0 4\n").unwrap();
+        assert_eq!(image.pixel(0, 0), Some(&Color::rgb(0, 0, 0)));
+        assert_eq!(image.pixel(1, 0), Some(&Color::rgb(255, 255, 255)));
+    }
+
+    #[test]
+    fn rejects_truncated_pnm() {
+        assert!(Image::decode(b"P6 2 2 255\n\x00\x00\x00").is_err());
+        assert!(Image::decode(b"P3 2 2 255\n0 0 0").is_err());
+        assert!(Image::decode(b"P5 2").is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_pnm_dimensions() {
+        assert!(Image::decode(b"P6 4294967296 4294967296 255\n").is_err());
+        assert!(Image::decode(b"P3 4294967296 4294967296 255\n").is_err());
+        let huge = format!("P5 {} 1 65535\n", usize::MAX / 2 + 1);
+        assert!(Image::decode(huge.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+
+/// Backend independent key identifier. Printable keys are reported as the
+/// lowercase character they produce without modifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Escape,
+    Backspace,
+    Tab,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Left,
+    Right,
+    Up,
+    Down,
+    Shift,
+    Control,
+    Alt,
+    Meta,
+    Function(u8),
+    /// Key without a portable name, holding the backend's key code.
+    Other(i32),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    Other(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    KeyDown {
+        key: Key,
+        modifiers: Modifiers,
+        repeat: bool,
+    },
+    KeyUp {
+        key: Key,
+        modifiers: Modifiers,
+    },
+    MouseMove {
+        x: f32,
+        y: f32,
+    },
+    MouseDown {
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    },
+    MouseUp {
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    },
+    /// Scroll amount, positive `y` scrolls away from the user.
+    MouseWheel {
+        x: f32,
+        y: f32,
+    },
+    Resized {
+        width: usize,
+        height: usize,
+    },
+    FocusGained,
+    FocusLost,
+}
+
+/// Input state as of the current frame, kept up to date from the events the
+/// renderer delivers.
+#[derive(Debug, Default)]
+pub struct InputState {
+    mouse_x: f32,
+    mouse_y: f32,
+    pmouse_x: f32,
+    pmouse_y: f32,
+    modifiers: Modifiers,
+    keys_down: HashSet<Key>,
+    buttons_down: HashSet<MouseButton>,
+}
+
+impl InputState {
+    pub fn mouse_x(&self) -> f32 {
+        self.mouse_x
+    }
+
+    pub fn mouse_y(&self) -> f32 {
+        self.mouse_y
+    }
+
+    /// Mouse x position during the previous frame.
+    pub fn pmouse_x(&self) -> f32 {
+        self.pmouse_x
+    }
+
+    /// Mouse y position during the previous frame.
+    pub fn pmouse_y(&self) -> f32 {
+        self.pmouse_y
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    pub fn key_is_down(&self, key: Key) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    pub fn any_key_is_down(&self) -> bool {
+        !self.keys_down.is_empty()
+    }
+
+    pub fn mouse_is_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn mouse_is_pressed(&self) -> bool {
+        !self.buttons_down.is_empty()
+    }
+
+    /// Remembers the mouse position as the previous one before the events
+    /// of a new frame are applied.
+    pub(crate) fn begin_frame(&mut self) {
+        self.pmouse_x = self.mouse_x;
+        self.pmouse_y = self.mouse_y;
+    }
+
+    pub(crate) fn apply(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::KeyDown { key, modifiers, .. } => {
+                self.keys_down.insert(key);
+                self.modifiers = modifiers;
+            }
+            InputEvent::KeyUp { key, modifiers } => {
+                self.keys_down.remove(&key);
+                self.modifiers = modifiers;
+            }
+            InputEvent::MouseMove { x, y } => {
+                self.mouse_x = x;
+                self.mouse_y = y;
+            }
+            InputEvent::MouseDown { button, x, y } => {
+                self.buttons_down.insert(button);
+                self.mouse_x = x;
+                self.mouse_y = y;
+            }
+            InputEvent::MouseUp { button, x, y } => {
+                self.buttons_down.remove(&button);
+                self.mouse_x = x;
+                self.mouse_y = y;
+            }
+            // releases are not reported once the window lost focus
+            InputEvent::FocusLost => {
+                self.keys_down.clear();
+                self.buttons_down.clear();
+                self.modifiers = Modifiers::default();
+            }
+            InputEvent::MouseWheel { .. }
+            | InputEvent::Resized { .. }
+            | InputEvent::FocusGained => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHIFT: Modifiers = Modifiers {
+        shift: true,
+        control: false,
+        alt: false,
+        meta: false,
+    };
+
+    fn key_down(key: Key) -> InputEvent {
+        InputEvent::KeyDown {
+            key,
+            modifiers: SHIFT,
+            repeat: false,
+        }
+    }
+
+    #[test]
+    fn previous_mouse_position_lags_one_frame() {
+        let mut input = InputState::default();
+        input.begin_frame();
+        input.apply(&InputEvent::MouseMove { x: 10.0, y: 20.0 });
+        input.apply(&InputEvent::MouseMove { x: 15.0, y: 25.0 });
+        assert_eq!((input.mouse_x(), input.mouse_y()), (15.0, 25.0));
+        assert_eq!((input.pmouse_x(), input.pmouse_y()), (0.0, 0.0));
+
+        input.begin_frame();
+        input.apply(&InputEvent::MouseDown {
+            button: MouseButton::Left,
+            x: 30.0,
+            y: 35.0,
+        });
+        assert_eq!((input.mouse_x(), input.mouse_y()), (30.0, 35.0));
+        assert_eq!((input.pmouse_x(), input.pmouse_y()), (15.0, 25.0));
+
+        input.begin_frame();
+        assert_eq!((input.pmouse_x(), input.pmouse_y()), (30.0, 35.0));
+    }
+
+    #[test]
+    fn keys_and_buttons_are_tracked_until_released() {
+        let mut input = InputState::default();
+        input.apply(&key_down(Key::Char('a')));
+        input.apply(&key_down(Key::Left));
+        assert!(input.key_is_down(Key::Char('a')) && input.key_is_down(Key::Left));
+        assert_eq!(input.modifiers(), SHIFT);
+
+        input.apply(&InputEvent::KeyUp {
+            key: Key::Char('a'),
+            modifiers: Modifiers::default(),
+        });
+        assert!(!input.key_is_down(Key::Char('a')));
+        assert!(input.any_key_is_down());
+        assert_eq!(input.modifiers(), Modifiers::default());
+
+        let button = |button| InputEvent::MouseDown {
+            button,
+            x: 1.0,
+            y: 2.0,
+        };
+        input.apply(&button(MouseButton::Left));
+        input.apply(&button(MouseButton::Right));
+        input.apply(&InputEvent::MouseUp {
+            button: MouseButton::Left,
+            x: 3.0,
+            y: 4.0,
+        });
+        assert!(!input.mouse_is_down(MouseButton::Left));
+        assert!(input.mouse_is_down(MouseButton::Right) && input.mouse_is_pressed());
+        assert_eq!((input.mouse_x(), input.mouse_y()), (3.0, 4.0));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything() {
+        let mut input = InputState::default();
+        input.apply(&key_down(Key::Shift));
+        input.apply(&InputEvent::MouseDown {
+            button: MouseButton::Middle,
+            x: 5.0,
+            y: 6.0,
+        });
+        input.apply(&InputEvent::FocusLost);
+        assert!(!input.any_key_is_down() && !input.mouse_is_pressed());
+        assert_eq!(input.modifiers(), Modifiers::default());
+        assert_eq!((input.mouse_x(), input.mouse_y()), (5.0, 6.0));
+    }
+}
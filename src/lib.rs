@@ -1,8 +1,23 @@
 pub mod canvas;
 pub mod color;
+pub mod display_list;
+pub mod error;
+pub mod font;
+pub mod image;
+pub mod input;
+pub mod noise;
 pub mod path;
+mod pdf;
 pub mod pixelbuffer;
 pub mod primitives;
+mod quantize;
+pub mod random;
+mod raster;
 pub mod renderer;
 pub mod sketch;
+pub mod stroke;
+pub mod transform;
 pub mod utils;
+pub mod vector;
+
+pub use error::Error;
@@ -0,0 +1,530 @@
+/// Seeded Perlin, simplex, OpenSimplex and value noise in one to four
+/// dimensions. The same seed yields the same values on every platform and
+/// backend as the permutation is built with a fixed PRNG.
+#[derive(Clone)]
+pub struct Noise {
+    seed: u64,
+    permutation: [u8; 512],
+}
+
+/// Fractal Brownian motion settings, summing `octaves` layers of noise where
+/// each layer's frequency is multiplied by `lacunarity` and its amplitude by
+/// `persistence`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fbm {
+    octaves: usize,
+    lacunarity: f32,
+    persistence: f32,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise::new(0)
+    }
+}
+
+impl Default for Fbm {
+    fn default() -> Self {
+        Fbm {
+            octaves: 4,
+            lacunarity: 2.0,
+            persistence: 0.5,
+        }
+    }
+}
+
+/// SplitMix64, small and stable so seeds keep producing the same noise.
+fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// Dot product of `offset` with the gradient picked by `hash`. Gradients
+/// point to the edge midpoints or corners of the unit hypercube.
+fn gradient<const N: usize>(hash: usize, offset: &[f32; N]) -> f32 {
+    if N == 1 {
+        let magnitude = 1.0 + (hash & 7) as f32;
+        let sign = if hash & 8 == 0 { 1.0 } else { -1.0 };
+        return sign * magnitude * offset[0] / 8.0;
+    }
+
+    let zero_axis = hash % (N + 1);
+    let signs = hash / (N + 1);
+    let mut dot = 0.0;
+    for (axis, component) in offset.iter().enumerate() {
+        if axis == zero_axis {
+            continue;
+        }
+        if signs >> axis & 1 == 0 {
+            dot += component;
+        } else {
+            dot -= component;
+        }
+    }
+    dot
+}
+
+/// Like `gradient`, with the OpenSimplex gradient sets: one large component
+/// along a hashed axis and small ones along all others.
+fn open_simplex_gradient<const N: usize>(hash: usize, offset: &[f32; N]) -> f32 {
+    if N == 1 {
+        return gradient(hash, offset);
+    }
+
+    let (major, minor) = OPEN_SIMPLEX_GRADIENTS[N - 2];
+    let major_axis = hash % N;
+    let signs = hash / N;
+    let mut dot = 0.0;
+    for (axis, component) in offset.iter().enumerate() {
+        let magnitude = if axis == major_axis { major } else { minor };
+        if signs >> axis & 1 == 0 {
+            dot += magnitude * component;
+        } else {
+            dot -= magnitude * component;
+        }
+    }
+    dot
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut table: [u8; 256] = [0; 256];
+        for (index, value) in table.iter_mut().enumerate() {
+            *value = index as u8;
+        }
+        let mut state = seed;
+        for index in (1..table.len()).rev() {
+            let other = (split_mix(&mut state) % (index as u64 + 1)) as usize;
+            table.swap(index, other);
+        }
+
+        let mut permutation = [0; 512];
+        for (index, value) in permutation.iter_mut().enumerate() {
+            *value = table[index & 255];
+        }
+        Noise { seed, permutation }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn hash<const N: usize>(&self, cell: &[i32; N]) -> usize {
+        cell.iter().fold(0, |hash, coordinate| {
+            self.permutation[hash + (*coordinate & 255) as usize] as usize
+        })
+    }
+
+    /// Blends the values `corner_value` computes for the corners of the
+    /// lattice cell around `point` from their hash and the offset of `point`
+    /// to them.
+    fn interpolate<const N: usize>(
+        &self,
+        point: [f32; N],
+        corner_value: impl Fn(usize, &[f32; N]) -> f32,
+    ) -> f32 {
+        let mut cell = [0i32; N];
+        let mut fraction = [0.0; N];
+        let mut faded = [0.0; N];
+        for axis in 0..N {
+            let floor = point[axis].floor();
+            cell[axis] = floor as i32;
+            fraction[axis] = point[axis] - floor;
+            faded[axis] = fade(fraction[axis]);
+        }
+
+        let mut sum = 0.0;
+        for corner in 0..1usize << N {
+            let mut corner_cell = cell;
+            let mut offset = fraction;
+            let mut weight = 1.0;
+            for axis in 0..N {
+                if corner >> axis & 1 == 1 {
+                    corner_cell[axis] += 1;
+                    offset[axis] -= 1.0;
+                    weight *= faded[axis];
+                } else {
+                    weight *= 1.0 - faded[axis];
+                }
+            }
+            sum += weight * corner_value(self.hash(&corner_cell), &offset);
+        }
+        sum
+    }
+
+    /// Classic improved Perlin noise in any dimension, roughly in -1.0 to 1.0.
+    fn perlin<const N: usize>(&self, point: [f32; N]) -> f32 {
+        let sum = self.interpolate(point, gradient);
+        (sum * PERLIN_SCALE[N - 1]).clamp(-1.0, 1.0)
+    }
+
+    /// Value noise in any dimension, random values at the lattice points
+    /// blended smoothly, in -1.0 to 1.0. Blobbier than gradient noise.
+    fn value<const N: usize>(&self, point: [f32; N]) -> f32 {
+        self.interpolate(point, |hash, _| hash as f32 / 127.5 - 1.0)
+    }
+
+    /// Simplex noise (Perlin 2001, following Gustavson's description) in any
+    /// dimension, roughly in -1.0 to 1.0. Cheaper than Perlin noise in higher
+    /// dimensions and free of its axis aligned artifacts.
+    fn simplex<const N: usize>(&self, point: [f32; N]) -> f32 {
+        let n = N as f32;
+        // a line needs no skewing, its simplices are the unit intervals
+        let (skew, unskew) = if N == 1 {
+            (0.0, 0.0)
+        } else {
+            (
+                ((n + 1.0).sqrt() - 1.0) / n,
+                (1.0 - 1.0 / (n + 1.0).sqrt()) / n,
+            )
+        };
+
+        let skewed = point.iter().sum::<f32>() * skew;
+        let mut cell = [0i32; N];
+        for axis in 0..N {
+            cell[axis] = (point[axis] + skewed).floor() as i32;
+        }
+        let unskewed = cell.iter().sum::<i32>() as f32 * unskew;
+        let mut origin = [0.0; N];
+        for axis in 0..N {
+            origin[axis] = point[axis] - (cell[axis] as f32 - unskewed);
+        }
+
+        // walk the simplex corners by stepping along the axes in order of
+        // decreasing distance from the cell origin
+        let mut order = [0usize; N];
+        for (index, axis) in order.iter_mut().enumerate() {
+            *axis = index;
+        }
+        order.sort_by(|a, b| origin[*b].total_cmp(&origin[*a]));
+
+        let radius = match N {
+            1 => 1.0,
+            2 => 0.5,
+            _ => 0.6,
+        };
+        let mut corner_cell = cell;
+        let mut sum = 0.0;
+        for corner in 0..=N {
+            if corner > 0 {
+                corner_cell[order[corner - 1]] += 1;
+            }
+            let mut offset = [0.0; N];
+            let mut distance = 0.0;
+            for axis in 0..N {
+                offset[axis] =
+                    origin[axis] - (corner_cell[axis] - cell[axis]) as f32 + corner as f32 * unskew;
+                distance += offset[axis] * offset[axis];
+            }
+            let falloff = radius - distance;
+            if falloff > 0.0 {
+                let falloff = falloff * falloff;
+                sum += falloff * falloff * gradient(self.hash(&corner_cell), &offset);
+            }
+        }
+        (sum * SIMPLEX_SCALE[N - 1]).clamp(-1.0, 1.0)
+    }
+
+    /// OpenSimplex noise (Kurt Spencer, 2014) in any dimension, roughly in
+    /// -1.0 to 1.0. Lattice points reach further than in simplex noise, which
+    /// gives smoother results with less visible lattice structure.
+    fn open_simplex<const N: usize>(&self, point: [f32; N]) -> f32 {
+        let n = N as f32;
+        let stretch = (1.0 / (n + 1.0).sqrt() - 1.0) / n;
+        let squish = ((n + 1.0).sqrt() - 1.0) / n;
+
+        let stretched = point.iter().sum::<f32>() * stretch;
+        let mut base = [0i32; N];
+        for axis in 0..N {
+            base[axis] = (point[axis] + stretched).floor() as i32;
+        }
+
+        // every lattice point within reach lies in the block reaching one
+        // cell before and two cells past the base cell along each axis
+        let mut sum = 0.0;
+        for block_index in 0..1usize << (2 * N) {
+            let mut vertex = base;
+            for (axis, coordinate) in vertex.iter_mut().enumerate() {
+                *coordinate += (block_index >> (2 * axis) & 3) as i32 - 1;
+            }
+            let squished = vertex.iter().sum::<i32>() as f32 * squish;
+            let mut offset = [0.0; N];
+            let mut distance = 0.0;
+            for axis in 0..N {
+                offset[axis] = point[axis] - (vertex[axis] as f32 + squished);
+                distance += offset[axis] * offset[axis];
+            }
+            let falloff = 2.0 - distance;
+            if falloff > 0.0 {
+                let falloff = falloff * falloff;
+                sum += falloff * falloff * open_simplex_gradient(self.hash(&vertex), &offset);
+            }
+        }
+        (sum * OPEN_SIMPLEX_SCALE[N - 1]).clamp(-1.0, 1.0)
+    }
+
+    pub fn perlin1(&self, x: f32) -> f32 {
+        self.perlin([x])
+    }
+
+    pub fn perlin2(&self, x: f32, y: f32) -> f32 {
+        self.perlin([x, y])
+    }
+
+    pub fn perlin3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.perlin([x, y, z])
+    }
+
+    pub fn perlin4(&self, x: f32, y: f32, z: f32, w: f32) -> f32 {
+        self.perlin([x, y, z, w])
+    }
+
+    pub fn simplex1(&self, x: f32) -> f32 {
+        self.simplex([x])
+    }
+
+    pub fn simplex2(&self, x: f32, y: f32) -> f32 {
+        self.simplex([x, y])
+    }
+
+    pub fn simplex3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.simplex([x, y, z])
+    }
+
+    pub fn simplex4(&self, x: f32, y: f32, z: f32, w: f32) -> f32 {
+        self.simplex([x, y, z, w])
+    }
+
+    pub fn open_simplex1(&self, x: f32) -> f32 {
+        self.open_simplex([x])
+    }
+
+    pub fn open_simplex2(&self, x: f32, y: f32) -> f32 {
+        self.open_simplex([x, y])
+    }
+
+    pub fn open_simplex3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.open_simplex([x, y, z])
+    }
+
+    pub fn open_simplex4(&self, x: f32, y: f32, z: f32, w: f32) -> f32 {
+        self.open_simplex([x, y, z, w])
+    }
+
+    pub fn value1(&self, x: f32) -> f32 {
+        self.value([x])
+    }
+
+    pub fn value2(&self, x: f32, y: f32) -> f32 {
+        self.value([x, y])
+    }
+
+    pub fn value3(&self, x: f32, y: f32, z: f32) -> f32 {
+        self.value([x, y, z])
+    }
+
+    pub fn value4(&self, x: f32, y: f32, z: f32, w: f32) -> f32 {
+        self.value([x, y, z, w])
+    }
+
+    /// Fractal Perlin noise, e.g. for terrain or clouds. Other noise types
+    /// can be layered with `Fbm::apply`.
+    pub fn fbm1(&self, x: f32, fbm: &Fbm) -> f32 {
+        fbm.apply(|frequency| self.perlin1(x * frequency))
+    }
+
+    pub fn fbm2(&self, x: f32, y: f32, fbm: &Fbm) -> f32 {
+        fbm.apply(|frequency| self.perlin2(x * frequency, y * frequency))
+    }
+
+    pub fn fbm3(&self, x: f32, y: f32, z: f32, fbm: &Fbm) -> f32 {
+        fbm.apply(|frequency| self.perlin3(x * frequency, y * frequency, z * frequency))
+    }
+
+    pub fn fbm4(&self, x: f32, y: f32, z: f32, w: f32, fbm: &Fbm) -> f32 {
+        fbm.apply(|frequency| {
+            self.perlin4(x * frequency, y * frequency, z * frequency, w * frequency)
+        })
+    }
+}
+
+impl Fbm {
+    pub fn new() -> Self {
+        Fbm::default()
+    }
+
+    pub fn octaves(mut self, octaves: usize) -> Self {
+        self.octaves = octaves.max(1);
+        self
+    }
+
+    pub fn lacunarity(mut self, lacunarity: f32) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    pub fn persistence(mut self, persistence: f32) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    /// Sums the octaves of any noise function, which is called with the
+    /// frequency to sample at. The result keeps the -1.0 to 1.0 range.
+    pub fn apply(&self, mut noise: impl FnMut(f32) -> f32) -> f32 {
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        let mut sum = 0.0;
+        let mut total_amplitude = 0.0;
+        for _ in 0..self.octaves {
+            sum += noise(frequency) * amplitude;
+            total_amplitude += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+        sum / total_amplitude
+    }
+}
+
+/// Brings the Perlin noise of each dimension to about -1.0 to 1.0.
+const PERLIN_SCALE: [f32; 4] = [2.0, 1.0, 0.9, 0.85];
+
+/// Brings the simplex noise of each dimension to about -1.0 to 1.0.
+const SIMPLEX_SCALE: [f32; 4] = [3.16, 70.0, 24.0, 24.0];
+
+/// Brings the OpenSimplex noise of each dimension to about -1.0 to 1.0.
+const OPEN_SIMPLEX_SCALE: [f32; 4] = [1.0 / 7.5, 1.0 / 47.0, 1.0 / 103.0, 1.0 / 30.0];
+
+/// Large and small gradient component of OpenSimplex noise in two to four
+/// dimensions.
+const OPEN_SIMPLEX_GRADIENTS: [(f32, f32); 3] = [(5.0, 2.0), (11.0, 4.0), (3.0, 1.0)];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sample points spread over positive and negative coordinates.
+    fn samples() -> impl Iterator<Item = [f32; 4]> {
+        (0..2000).map(|index| {
+            let t = index as f32;
+            [
+                (t * 0.618).sin() * 40.0,
+                (t * 0.371).cos() * 40.0 + t * 0.01,
+                (t * 0.913).sin() * 25.0 - 3.0,
+                t * 0.037 - 30.0,
+            ]
+        })
+    }
+
+    fn all_noise(noise: &Noise, [x, y, z, w]: [f32; 4]) -> [f32; 16] {
+        [
+            noise.perlin1(x),
+            noise.perlin2(x, y),
+            noise.perlin3(x, y, z),
+            noise.perlin4(x, y, z, w),
+            noise.simplex1(x),
+            noise.simplex2(x, y),
+            noise.simplex3(x, y, z),
+            noise.simplex4(x, y, z, w),
+            noise.open_simplex1(x),
+            noise.open_simplex2(x, y),
+            noise.open_simplex3(x, y, z),
+            noise.open_simplex4(x, y, z, w),
+            noise.value1(x),
+            noise.value2(x, y),
+            noise.value3(x, y, z),
+            noise.value4(x, y, z, w),
+        ]
+    }
+
+    #[test]
+    fn values_stay_in_range_and_vary() {
+        let noise = Noise::new(42);
+        let mut min = [f32::MAX; 16];
+        let mut max = [f32::MIN; 16];
+        for point in samples() {
+            for (index, value) in all_noise(&noise, point).into_iter().enumerate() {
+                assert!(
+                    (-1.0..=1.0).contains(&value),
+                    "noise {} gave {}",
+                    index,
+                    value
+                );
+                min[index] = min[index].min(value);
+                max[index] = max[index].max(value);
+            }
+        }
+        for index in 0..16 {
+            assert!(
+                max[index] - min[index] > 0.5,
+                "noise {} is nearly flat",
+                index
+            );
+        }
+    }
+
+    #[test]
+    fn same_seed_reproduces_values() {
+        let (first, second, other) = (Noise::new(7), Noise::new(7), Noise::new(8));
+        let mut differs = [false; 16];
+        for point in samples().take(200) {
+            let values = all_noise(&first, point);
+            assert_eq!(values, all_noise(&second, point));
+            for (index, (a, b)) in values.iter().zip(all_noise(&other, point)).enumerate() {
+                differs[index] |= *a != b;
+            }
+        }
+        assert!(differs.iter().all(|differs| *differs));
+    }
+
+    #[test]
+    fn gradient_noise_vanishes_on_the_lattice() {
+        let noise = Noise::new(3);
+        for x in -5..5 {
+            for y in -5..5 {
+                let (x, y) = (x as f32, y as f32);
+                assert_eq!(noise.perlin2(x, y), 0.0);
+                assert_eq!(noise.perlin3(x, y, 2.0), 0.0);
+                assert_eq!(noise.simplex1(x), 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn noise_is_continuous() {
+        let noise = Noise::new(11);
+        for point in samples().take(500) {
+            let nudged = point.map(|coordinate| coordinate + 1e-3);
+            for (a, b) in all_noise(&noise, point)
+                .iter()
+                .zip(all_noise(&noise, nudged))
+            {
+                assert!((a - b).abs() < 0.05, "{} jumps to {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn fbm_keeps_range_and_single_octave_matches_noise() {
+        let noise = Noise::new(5);
+        let single = Fbm::new().octaves(1);
+        let layered = Fbm::new().octaves(6).persistence(0.7);
+        for [x, y, z, w] in samples().take(300) {
+            assert_eq!(noise.fbm2(x, y, &single), noise.perlin2(x, y));
+            for value in [
+                noise.fbm1(x, &layered),
+                noise.fbm2(x, y, &layered),
+                noise.fbm3(x, y, z, &layered),
+                noise.fbm4(x, y, z, w, &layered),
+            ] {
+                assert!((-1.0..=1.0).contains(&value));
+            }
+        }
+    }
+}
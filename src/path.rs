@@ -1,16 +1,238 @@
-use crate::primitives::IntPoint;
+use crate::primitives::FloatPoint;
+use std::f32::consts::PI;
 
-#[derive(Clone)]
+const MAX_SUBDIVISIONS: usize = 16;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum PathSegment {
-    MoveTo(IntPoint),
-    LineTo(IntPoint),
+    MoveTo(FloatPoint),
+    LineTo(FloatPoint),
+    QuadTo(FloatPoint, FloatPoint),
+    CubicTo(FloatPoint, FloatPoint, FloatPoint),
+    ArcTo {
+        radius_x: f32,
+        radius_y: f32,
+        rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        end: FloatPoint,
+    },
+    Close,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    EvenOdd,
+    NonZero,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Path {
     segments: Vec<PathSegment>,
 }
 
+type Point = (f32, f32);
+
+fn to_point(pt: &FloatPoint) -> Point {
+    (pt.x, pt.y)
+}
+
+fn midpoint(a: Point, b: Point) -> Point {
+    ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+}
+
+/// Distance of `pt` to the line through `start` and `end`.
+fn distance_to_chord(pt: Point, start: Point, end: Point) -> f32 {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        return ((pt.0 - start.0).powi(2) + (pt.1 - start.1).powi(2)).sqrt();
+    }
+    ((pt.0 - start.0) * dy - (pt.1 - start.1) * dx).abs() / length
+}
+
+/// Recursively splits the curve in half (de Casteljau) until the control
+/// points lie within `tolerance` of the chord.
+fn flatten_cubic(points: &mut Vec<Point>, curve: [Point; 4], tolerance: f32, depth: usize) {
+    let [p0, p1, p2, p3] = curve;
+    let flat =
+        distance_to_chord(p1, p0, p3) <= tolerance && distance_to_chord(p2, p0, p3) <= tolerance;
+    if flat || depth >= MAX_SUBDIVISIONS {
+        points.push(p3);
+        return;
+    }
+
+    let p01 = midpoint(p0, p1);
+    let p12 = midpoint(p1, p2);
+    let p23 = midpoint(p2, p3);
+    let p012 = midpoint(p01, p12);
+    let p123 = midpoint(p12, p23);
+    let split = midpoint(p012, p123);
+    flatten_cubic(points, [p0, p01, p012, split], tolerance, depth + 1);
+    flatten_cubic(points, [split, p123, p23, p3], tolerance, depth + 1);
+}
+
+fn flatten_quad(points: &mut Vec<Point>, start: Point, control: Point, end: Point, tolerance: f32) {
+    // raise the degree, a quadratic curve is a cubic with both controls on it
+    let c1 = (
+        start.0 + 2.0 / 3.0 * (control.0 - start.0),
+        start.1 + 2.0 / 3.0 * (control.1 - start.1),
+    );
+    let c2 = (
+        end.0 + 2.0 / 3.0 * (control.0 - end.0),
+        end.1 + 2.0 / 3.0 * (control.1 - end.1),
+    );
+    flatten_cubic(points, [start, c1, c2, end], tolerance, 0);
+}
+
+/// Elliptical arc in center parameterization.
+struct EllipticArc {
+    center: Point,
+    radius_x: f32,
+    radius_y: f32,
+    sin_phi: f32,
+    cos_phi: f32,
+    start_angle: f32,
+    delta: f32,
+}
+
+impl EllipticArc {
+    /// Converts the SVG endpoint parameterization, `None` if the arc
+    /// degenerates into a straight line or nothing at all.
+    /// https://www.w3.org/TR/SVG11/implnote.html#ArcImplementationNotes
+    fn new(
+        start: Point,
+        end: Point,
+        radius_x: f32,
+        radius_y: f32,
+        rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+    ) -> Option<Self> {
+        let (mut rx, mut ry) = (radius_x.abs(), radius_y.abs());
+        if start == end || rx == 0.0 || ry == 0.0 {
+            return None;
+        }
+
+        let (sin_phi, cos_phi) = rotation.sin_cos();
+        let half_dx = (start.0 - end.0) / 2.0;
+        let half_dy = (start.1 - end.1) / 2.0;
+        let x1 = cos_phi * half_dx + sin_phi * half_dy;
+        let y1 = -sin_phi * half_dx + cos_phi * half_dy;
+
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1.0 {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let sign = if large_arc == sweep { -1.0 } else { 1.0 };
+        let coefficient = sign * (numerator / denominator).max(0.0).sqrt();
+        let center_x1 = coefficient * rx * y1 / ry;
+        let center_y1 = -coefficient * ry * x1 / rx;
+        let center_x = cos_phi * center_x1 - sin_phi * center_y1 + (start.0 + end.0) / 2.0;
+        let center_y = sin_phi * center_x1 + cos_phi * center_y1 + (start.1 + end.1) / 2.0;
+
+        let angle = |ux: f32, uy: f32| uy.atan2(ux);
+        let start_angle = angle((x1 - center_x1) / rx, (y1 - center_y1) / ry);
+        let end_angle = angle((-x1 - center_x1) / rx, (-y1 - center_y1) / ry);
+        let mut delta = end_angle - start_angle;
+        if sweep && delta < 0.0 {
+            delta += 2.0 * PI;
+        } else if !sweep && delta > 0.0 {
+            delta -= 2.0 * PI;
+        }
+
+        Some(EllipticArc {
+            center: (center_x, center_y),
+            radius_x: rx,
+            radius_y: ry,
+            sin_phi,
+            cos_phi,
+            start_angle,
+            delta,
+        })
+    }
+
+    fn point_at(&self, theta: f32) -> Point {
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        (
+            self.center.0 + self.radius_x * self.cos_phi * cos_theta
+                - self.radius_y * self.sin_phi * sin_theta,
+            self.center.1
+                + self.radius_x * self.sin_phi * cos_theta
+                + self.radius_y * self.cos_phi * sin_theta,
+        )
+    }
+
+    fn tangent_at(&self, theta: f32) -> Point {
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        (
+            -self.radius_x * self.cos_phi * sin_theta - self.radius_y * self.sin_phi * cos_theta,
+            -self.radius_x * self.sin_phi * sin_theta + self.radius_y * self.cos_phi * cos_theta,
+        )
+    }
+
+    /// Control points of cubic curves following the arc, one per quarter turn at most.
+    fn cubics(&self, end: Point) -> Vec<[Point; 3]> {
+        let pieces = (self.delta.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+        let step = self.delta / pieces as f32;
+        let handle = 4.0 / 3.0 * (step / 4.0).tan();
+        (0..pieces)
+            .map(|piece| {
+                let theta1 = self.start_angle + step * piece as f32;
+                let theta2 = theta1 + step;
+                let (start, tangent1) = (self.point_at(theta1), self.tangent_at(theta1));
+                let tangent2 = self.tangent_at(theta2);
+                let stop = if piece + 1 == pieces {
+                    end
+                } else {
+                    self.point_at(theta2)
+                };
+                [
+                    (start.0 + handle * tangent1.0, start.1 + handle * tangent1.1),
+                    (stop.0 - handle * tangent2.0, stop.1 - handle * tangent2.1),
+                    stop,
+                ]
+            })
+            .collect()
+    }
+}
+
+fn flatten_arc(points: &mut Vec<Point>, end: Point, arc: Option<EllipticArc>, tolerance: f32) {
+    let arc = match arc {
+        Some(arc) => arc,
+        None => {
+            points.push(end);
+            return;
+        }
+    };
+
+    let radius = arc.radius_x.max(arc.radius_y);
+    let max_step = if tolerance < radius {
+        2.0 * (1.0 - tolerance / radius).acos()
+    } else {
+        PI / 2.0
+    };
+    let steps = (arc.delta.abs() / max_step).ceil().max(1.0) as usize;
+
+    for step in 1..steps {
+        points.push(arc.point_at(arc.start_angle + arc.delta * step as f32 / steps as f32));
+    }
+    points.push(end);
+}
+
+/// Path element of the reduced form vector backends draw with.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Curve {
+    MoveTo(Point),
+    LineTo(Point),
+    CubicTo(Point, Point, Point),
+    Close,
+}
+
 impl Path {
     pub fn new() -> Self {
         Path {
@@ -18,12 +240,56 @@ impl Path {
         }
     }
 
-    pub fn move_to(&mut self, pt: &IntPoint) {
-        self.segments.push(PathSegment::MoveTo(pt.clone()));
+    pub fn move_to(&mut self, pt: impl Into<FloatPoint>) {
+        self.segments.push(PathSegment::MoveTo(pt.into()));
+    }
+
+    pub fn line_to(&mut self, pt: impl Into<FloatPoint>) {
+        self.segments.push(PathSegment::LineTo(pt.into()));
+    }
+
+    pub fn quad_to(&mut self, control: impl Into<FloatPoint>, pt: impl Into<FloatPoint>) {
+        self.segments
+            .push(PathSegment::QuadTo(control.into(), pt.into()));
     }
 
-    pub fn line_to(&mut self, pt: &IntPoint) {
-        self.segments.push(PathSegment::LineTo(pt.clone()));
+    pub fn cubic_to(
+        &mut self,
+        control1: impl Into<FloatPoint>,
+        control2: impl Into<FloatPoint>,
+        pt: impl Into<FloatPoint>,
+    ) {
+        self.segments.push(PathSegment::CubicTo(
+            control1.into(),
+            control2.into(),
+            pt.into(),
+        ));
+    }
+
+    /// Elliptical arc to `pt` following the SVG arc semantics, `rotation` of
+    /// the ellipse is given in radians.
+    pub fn arc_to(
+        &mut self,
+        radius_x: f32,
+        radius_y: f32,
+        rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        pt: impl Into<FloatPoint>,
+    ) {
+        self.segments.push(PathSegment::ArcTo {
+            radius_x,
+            radius_y,
+            rotation,
+            large_arc,
+            sweep,
+            end: pt.into(),
+        });
+    }
+
+    /// Connects the current sub-path back to the point it was started at.
+    pub fn close(&mut self) {
+        self.segments.push(PathSegment::Close);
     }
 
     pub fn clear(&mut self) {
@@ -37,4 +303,269 @@ impl Path {
     pub fn segment_count(&self) -> usize {
         self.segments.len()
     }
+
+    /// Whether every sub-path is closed, open sub-paths are only stroked.
+    pub fn is_closed(&self) -> bool {
+        self.subpaths()
+            .iter()
+            .filter(|(_, segments, _)| !segments.is_empty())
+            .all(|(_, _, closed)| *closed)
+    }
+
+    /// The closed sub-paths on their own, each started with a `MoveTo`.
+    pub(crate) fn closed_subpaths(&self) -> Path {
+        let mut path = Path::new();
+        for (start, segments, closed) in self.subpaths() {
+            if closed && !segments.is_empty() {
+                path.move_to(start);
+                path.segments.extend(segments.into_iter().cloned());
+                path.close();
+            }
+        }
+        path
+    }
+
+    /// Start point, drawing segments and closedness of every sub-path. Like
+    /// in `polylines` a sub-path without `MoveTo` starts where the previous
+    /// one did, or at the origin.
+    fn subpaths(&self) -> Vec<(FloatPoint, Vec<&PathSegment>, bool)> {
+        let mut subpaths: Vec<(FloatPoint, Vec<&PathSegment>, bool)> = Vec::new();
+        let mut start = FloatPoint::new(0.0, 0.0);
+        let mut needs_start = true;
+        for segment in &self.segments {
+            match segment {
+                PathSegment::MoveTo(pt) => {
+                    start = *pt;
+                    subpaths.push((start, Vec::new(), false));
+                    needs_start = false;
+                }
+                PathSegment::Close => {
+                    if let Some((_, _, closed)) = subpaths.last_mut() {
+                        if !needs_start {
+                            *closed = true;
+                        }
+                    }
+                    needs_start = true;
+                }
+                _ => {
+                    if needs_start {
+                        subpaths.push((start, Vec::new(), false));
+                        needs_start = false;
+                    }
+                    subpaths.last_mut().unwrap().1.push(segment);
+                }
+            }
+        }
+        subpaths
+    }
+
+    /// Splits the path into its sub-paths, each given as its points and
+    /// whether it was closed. Curves are flattened into line segments that
+    /// deviate at most `tolerance` pixels from the exact curve.
+    pub(crate) fn polylines(&self, tolerance: f32) -> Vec<(Vec<Point>, bool)> {
+        let tolerance = tolerance.max(0.01);
+        let mut polylines: Vec<(Vec<Point>, bool)> = Vec::new();
+        let mut subpath_start = (0.0, 0.0);
+        let mut needs_start = true;
+
+        for segment in &self.segments {
+            if let PathSegment::MoveTo(pt) = segment {
+                subpath_start = to_point(pt);
+                polylines.push((vec![subpath_start], false));
+                needs_start = false;
+                continue;
+            }
+            if let PathSegment::Close = segment {
+                if let Some((_, closed)) = polylines.last_mut() {
+                    if !needs_start {
+                        *closed = true;
+                    }
+                }
+                needs_start = true;
+                continue;
+            }
+
+            if needs_start {
+                polylines.push((vec![subpath_start], false));
+                needs_start = false;
+            }
+            let (points, _) = polylines.last_mut().unwrap();
+            let cursor = *points.last().unwrap();
+
+            match segment {
+                PathSegment::LineTo(pt) => points.push(to_point(pt)),
+                PathSegment::QuadTo(control, pt) => {
+                    flatten_quad(points, cursor, to_point(control), to_point(pt), tolerance);
+                }
+                PathSegment::CubicTo(control1, control2, pt) => {
+                    let curve = [cursor, to_point(control1), to_point(control2), to_point(pt)];
+                    flatten_cubic(points, curve, tolerance, 0);
+                }
+                PathSegment::ArcTo {
+                    radius_x,
+                    radius_y,
+                    rotation,
+                    large_arc,
+                    sweep,
+                    end,
+                } => {
+                    let end = to_point(end);
+                    let arc = EllipticArc::new(
+                        cursor, end, *radius_x, *radius_y, *rotation, *large_arc, *sweep,
+                    );
+                    flatten_arc(points, end, arc, tolerance)
+                }
+                PathSegment::MoveTo(_) | PathSegment::Close => {}
+            }
+        }
+        polylines
+    }
+
+    /// The path with quadratic curves and arcs turned into cubic curves,
+    /// which is all PDF content streams can describe.
+    pub(crate) fn curves(&self) -> Vec<Curve> {
+        let mut curves = Vec::with_capacity(self.segments.len());
+        let mut subpath_start = (0.0, 0.0);
+        let mut cursor = (0.0, 0.0);
+        // PDF needs a current point, paths start at the origin like `polylines`
+        if !matches!(self.segments.first(), None | Some(PathSegment::MoveTo(_))) {
+            curves.push(Curve::MoveTo(cursor));
+        }
+        for segment in &self.segments {
+            match segment {
+                PathSegment::MoveTo(pt) => {
+                    subpath_start = to_point(pt);
+                    cursor = subpath_start;
+                    curves.push(Curve::MoveTo(cursor));
+                }
+                PathSegment::LineTo(pt) => {
+                    cursor = to_point(pt);
+                    curves.push(Curve::LineTo(cursor));
+                }
+                PathSegment::QuadTo(control, pt) => {
+                    let (control, end) = (to_point(control), to_point(pt));
+                    curves.push(Curve::CubicTo(
+                        (
+                            cursor.0 + 2.0 / 3.0 * (control.0 - cursor.0),
+                            cursor.1 + 2.0 / 3.0 * (control.1 - cursor.1),
+                        ),
+                        (
+                            end.0 + 2.0 / 3.0 * (control.0 - end.0),
+                            end.1 + 2.0 / 3.0 * (control.1 - end.1),
+                        ),
+                        end,
+                    ));
+                    cursor = end;
+                }
+                PathSegment::CubicTo(control1, control2, pt) => {
+                    cursor = to_point(pt);
+                    curves.push(Curve::CubicTo(
+                        to_point(control1),
+                        to_point(control2),
+                        cursor,
+                    ));
+                }
+                PathSegment::ArcTo {
+                    radius_x,
+                    radius_y,
+                    rotation,
+                    large_arc,
+                    sweep,
+                    end,
+                } => {
+                    let end = to_point(end);
+                    match EllipticArc::new(
+                        cursor, end, *radius_x, *radius_y, *rotation, *large_arc, *sweep,
+                    ) {
+                        Some(arc) => curves.extend(arc.cubics(end).into_iter().map(
+                            |[control1, control2, pt]| Curve::CubicTo(control1, control2, pt),
+                        )),
+                        None if end != cursor => curves.push(Curve::LineTo(end)),
+                        None => {}
+                    }
+                    cursor = end;
+                }
+                PathSegment::Close => {
+                    curves.push(Curve::Close);
+                    cursor = subpath_start;
+                }
+            }
+        }
+        curves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closed_subpaths_are_kept() {
+        let mut path = Path::new();
+        path.move_to((0.0, 0.0));
+        path.line_to((10.0, 0.0));
+        path.line_to((10.0, 10.0));
+        path.close();
+        assert!(path.is_closed());
+
+        path.move_to((20.0, 20.0));
+        path.line_to((30.0, 20.0));
+        assert!(!path.is_closed());
+
+        let closed = path.closed_subpaths();
+        assert_eq!(
+            closed.segments(),
+            &path.segments()[..4].to_vec(),
+            "only the first sub-path is closed"
+        );
+    }
+
+    #[test]
+    fn subpath_after_close_starts_at_previous_start() {
+        let mut path = Path::new();
+        path.move_to((5.0, 5.0));
+        path.line_to((10.0, 5.0));
+        path.close();
+        path.line_to((5.0, 10.0));
+        path.line_to((0.0, 10.0));
+        path.close();
+
+        let polylines = path.polylines(0.25);
+        assert_eq!(polylines.len(), 2);
+        assert_eq!(polylines[1].0[0], (5.0, 5.0));
+        assert!(polylines.iter().all(|(_, closed)| *closed));
+        assert_eq!(
+            path.closed_subpaths().segments()[3],
+            PathSegment::MoveTo(FloatPoint::new(5.0, 5.0))
+        );
+    }
+
+    #[test]
+    fn lone_move_to_does_not_open_path() {
+        let mut path = Path::new();
+        path.move_to((0.0, 0.0));
+        path.line_to((1.0, 0.0));
+        path.line_to((1.0, 1.0));
+        path.close();
+        path.move_to((3.0, 3.0));
+        assert!(path.is_closed());
+    }
+
+    #[test]
+    fn curves_without_move_to_start_at_origin() {
+        let mut path = Path::new();
+        path.line_to((5.0, 5.0));
+        path.close();
+        path.line_to((0.0, 5.0));
+
+        assert_eq!(
+            path.curves(),
+            vec![
+                Curve::MoveTo((0.0, 0.0)),
+                Curve::LineTo((5.0, 5.0)),
+                Curve::Close,
+                Curve::LineTo((0.0, 5.0)),
+            ]
+        );
+    }
 }
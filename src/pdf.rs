@@ -0,0 +1,1059 @@
+use crate::color::{BlendMode, Color};
+use crate::display_list::{number, DisplayList, RecordedShape, Shape, ShapeStyle, TextRun};
+use crate::font::{Font, FontFace, FontWeight};
+use crate::image::{Image, ImageSampling};
+use crate::path::{Curve, FillRule};
+use crate::stroke::{LineCap, LineJoin, MITER_LIMIT};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+const CATALOG_ID: usize = 1;
+const PAGES_ID: usize = 2;
+
+/// Type3 fonts address glyphs with a single byte.
+const GLYPHS_PER_FONT: usize = 256;
+
+/// Distance of the Bézier handles from the ends of a quarter ellipse.
+const KAPPA: f32 = 0.552_284_8;
+
+/// Glyphs of one font face at one size, drawn from the same coverage masks
+/// the canvas renders text with. Used for the bundled bitmap font and fonts
+/// that can not be embedded.
+struct Type3Font {
+    id: usize,
+    font: Option<Font>,
+    size: usize,
+    weight: FontWeight,
+    chars: Vec<char>,
+}
+
+/// A TrueType font embedded as a whole, text selects its glyphs by index so
+/// the outlines scale to any size.
+struct EmbeddedFont {
+    id: usize,
+    font: Font,
+    /// Used glyphs with the character each one was first drawn for.
+    glyphs: BTreeMap<u16, char>,
+}
+
+/// PDF document written page by page, a canvas pixel becomes a point.
+pub(crate) struct PdfDocument<W: Write> {
+    writer: W,
+    offset: usize,
+    object_offsets: Vec<usize>,
+    page_ids: Vec<usize>,
+    fonts: Vec<Type3Font>,
+    embedded_fonts: Vec<EmbeddedFont>,
+}
+
+fn color_operands(color: Color) -> String {
+    format!(
+        "{} {} {}",
+        number(color.red as f32 / 255.0),
+        number(color.green as f32 / 255.0),
+        number(color.blue as f32 / 255.0)
+    )
+}
+
+/// Alpha of fill and stroke and the blend mode, written as one `ExtGState`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct GraphicsState {
+    fill_alpha: u8,
+    stroke_alpha: u8,
+    blend_mode: &'static str,
+}
+
+impl GraphicsState {
+    fn new(fill_alpha: u8, stroke_alpha: u8, mode: BlendMode) -> Self {
+        let blend_mode = match mode {
+            BlendMode::SourceOver => "Normal",
+            // PDF has no additive blending, screen comes closest to it
+            BlendMode::Add | BlendMode::Screen => "Screen",
+            BlendMode::Multiply => "Multiply",
+            BlendMode::Lighten => "Lighten",
+            BlendMode::Darken => "Darken",
+            BlendMode::Difference => "Difference",
+        };
+        GraphicsState {
+            fill_alpha,
+            stroke_alpha,
+            blend_mode,
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == GraphicsState::new(255, 255, BlendMode::SourceOver)
+    }
+
+    fn name(&self) -> String {
+        let mut name = format!("GS{}_{}", self.fill_alpha, self.stroke_alpha);
+        if self.blend_mode != "Normal" {
+            let _ = write!(name, "_{}", self.blend_mode);
+        }
+        name
+    }
+
+    fn dictionary(&self) -> String {
+        let mut dictionary = format!(
+            "<< /ca {} /CA {}",
+            number(self.fill_alpha as f32 / 255.0),
+            number(self.stroke_alpha as f32 / 255.0)
+        );
+        if self.blend_mode != "Normal" {
+            let _ = write!(dictionary, " /BM /{}", self.blend_mode);
+        }
+        dictionary.push_str(" >>");
+        dictionary
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+/// Only fonts with TrueType outlines can be embedded as `FontFile2`, fonts
+/// with CFF outlines and font collections are not.
+fn has_true_type_outlines(font: &Font) -> bool {
+    matches!(font.data().get(..4), Some([0, 1, 0, 0]) | Some(b"true"))
+}
+
+/// CMap that lets viewers copy the text out of the document, `codes` pairs
+/// character codes `bytes` wide with the character each one stands for.
+fn to_unicode(bytes: usize, codes: &[(usize, char)]) -> String {
+    let width = 2 * bytes;
+    let mut cmap = format!(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<{:0width$X}> <{:0width$X}>\nendcodespacerange\n",
+        0,
+        (1usize << (8 * bytes)) - 1,
+        width = width
+    );
+    for chunk in codes.chunks(100) {
+        let _ = writeln!(cmap, "{} beginbfchar", chunk.len());
+        for (code, char) in chunk {
+            let utf16: String = char
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            let _ = writeln!(cmap, "<{:0width$X}> <{}>", code, utf16, width = width);
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+impl<W: Write> PdfDocument<W> {
+    pub(crate) fn new(writer: W) -> io::Result<Self> {
+        let mut document = PdfDocument {
+            writer,
+            offset: 0,
+            // the catalog and page tree are written last but have fixed ids
+            object_offsets: vec![0; 2],
+            page_ids: Vec::new(),
+            fonts: Vec::new(),
+            embedded_fonts: Vec::new(),
+        };
+        document.write(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n")?;
+        Ok(document)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes)?;
+        self.offset += bytes.len();
+        Ok(())
+    }
+
+    fn allocate(&mut self) -> usize {
+        self.object_offsets.push(0);
+        self.object_offsets.len()
+    }
+
+    fn write_object(&mut self, id: usize, body: &str) -> io::Result<()> {
+        self.object_offsets[id - 1] = self.offset;
+        self.write(format!("{} 0 obj\n{}\nendobj\n", id, body).as_bytes())
+    }
+
+    fn write_stream(&mut self, id: usize, entries: &str, data: &[u8]) -> io::Result<()> {
+        self.object_offsets[id - 1] = self.offset;
+        self.write(
+            format!(
+                "{} 0 obj\n<< /Length {}{} >>\nstream\n",
+                id,
+                data.len(),
+                entries
+            )
+            .as_bytes(),
+        )?;
+        self.write(data)?;
+        self.write(b"\nendstream\nendobj\n")
+    }
+
+    /// Index into `fonts` of the font holding `char`, adding it if needed.
+    fn font_for(
+        &mut self,
+        font: &Option<Font>,
+        size: usize,
+        weight: FontWeight,
+        char: char,
+    ) -> usize {
+        let same_face = |candidate: &Type3Font| {
+            candidate.font == *font && candidate.size == size && candidate.weight == weight
+        };
+        if let Some(index) = self
+            .fonts
+            .iter()
+            .position(|candidate| same_face(candidate) && candidate.chars.contains(&char))
+        {
+            return index;
+        }
+        let index =
+            match self.fonts.iter().position(|candidate| {
+                same_face(candidate) && candidate.chars.len() < GLYPHS_PER_FONT
+            }) {
+                Some(index) => index,
+                None => {
+                    let id = self.allocate();
+                    self.fonts.push(Type3Font {
+                        id,
+                        font: font.clone(),
+                        size,
+                        weight,
+                        chars: Vec::new(),
+                    });
+                    self.fonts.len() - 1
+                }
+            };
+        self.fonts[index].chars.push(char);
+        index
+    }
+
+    /// Index into `embedded_fonts` of `font`, adding it if needed.
+    fn embedded_font_for(&mut self, font: &Font) -> usize {
+        if let Some(index) = self
+            .embedded_fonts
+            .iter()
+            .position(|candidate| candidate.font == *font)
+        {
+            return index;
+        }
+        let id = self.allocate();
+        self.embedded_fonts.push(EmbeddedFont {
+            id,
+            font: font.clone(),
+            glyphs: BTreeMap::new(),
+        });
+        self.embedded_fonts.len() - 1
+    }
+
+    /// Appends the display list as a new page of the canvas' size.
+    pub(crate) fn add_page(&mut self, display_list: &DisplayList) -> io::Result<()> {
+        let (width, height) = (display_list.width(), display_list.height());
+        let mut content = String::new();
+        let mut graphics_states = BTreeSet::new();
+        let mut used_fonts = BTreeMap::new();
+        let mut images = Vec::new();
+
+        if let Some(background) = display_list.background() {
+            let _ = writeln!(
+                content,
+                "{} rg 0 0 {} {} re f",
+                color_operands(background),
+                width,
+                height
+            );
+        }
+        // flip to the canvas' y axis pointing down and address pixel centers
+        let _ = writeln!(content, "1 0 0 -1 0.5 {} cm", number(height as f32 - 0.5));
+        for recorded in display_list.shapes() {
+            self.write_shape(
+                &mut content,
+                recorded,
+                &mut graphics_states,
+                &mut used_fonts,
+                &mut images,
+            );
+        }
+
+        let content_id = self.allocate();
+        self.write_stream(content_id, "", content.as_bytes())?;
+
+        let mut resources = String::new();
+        if !images.is_empty() {
+            resources.push_str("/XObject <<");
+            for (index, (image, sampling)) in images.iter().enumerate() {
+                let id = self.write_image(image, *sampling)?;
+                let _ = write!(resources, " /Im{} {} 0 R", index, id);
+            }
+            resources.push_str(" >> ");
+        }
+        if !used_fonts.is_empty() {
+            resources.push_str("/Font <<");
+            for (name, id) in &used_fonts {
+                let _ = write!(resources, " /{} {} 0 R", name, id);
+            }
+            resources.push_str(" >> ");
+        }
+        if !graphics_states.is_empty() {
+            resources.push_str("/ExtGState <<");
+            for state in &graphics_states {
+                let _ = write!(resources, " /{} {}", state.name(), state.dictionary());
+            }
+            resources.push_str(" >> ");
+        }
+
+        let page_id = self.allocate();
+        self.write_object(
+            page_id,
+            &format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << {}>> /Contents {} 0 R >>",
+                PAGES_ID, width, height, resources, content_id
+            ),
+        )?;
+        self.page_ids.push(page_id);
+        Ok(())
+    }
+
+    fn write_shape(
+        &mut self,
+        content: &mut String,
+        recorded: &RecordedShape,
+        graphics_states: &mut BTreeSet<GraphicsState>,
+        used_fonts: &mut BTreeMap<String, usize>,
+        images: &mut Vec<(Arc<Image>, ImageSampling)>,
+    ) {
+        if let Some(parts) = recorded.split_open_path() {
+            for part in &parts {
+                self.write_shape(content, part, graphics_states, used_fonts, images);
+            }
+            return;
+        }
+        let style = &recorded.style;
+        let matrix = style.matrix;
+        if let Shape::Image {
+            image,
+            dest,
+            sampling,
+        } = &recorded.shape
+        {
+            // images cover pixels from corner to corner, unlike the other
+            // shapes, and are drawn into the unit square bottom row first
+            content.push_str("q\n");
+            let state = GraphicsState::new(255, 255, style.blend_mode);
+            if !state.is_default() {
+                let _ = writeln!(content, "/{} gs", state.name());
+                graphics_states.insert(state);
+            }
+            content.push_str("1 0 0 1 -0.5 -0.5 cm\n");
+            if !matrix.is_identity() {
+                let _ = writeln!(
+                    content,
+                    "{} {} {} {} {} {} cm",
+                    number(matrix.a),
+                    number(matrix.b),
+                    number(matrix.c),
+                    number(matrix.d),
+                    number(matrix.e),
+                    number(matrix.f)
+                );
+            }
+            let _ = writeln!(
+                content,
+                "{} 0 0 {} {} {} cm\n/Im{} Do\nQ",
+                number(dest.width),
+                number(-dest.height),
+                number(dest.x()),
+                number(dest.y() + dest.height),
+                images.len()
+            );
+            images.push((image.clone(), *sampling));
+            return;
+        }
+        let (fill, stroke) = match recorded.shape {
+            Shape::Point(_) => (style.stroke, None),
+            Shape::Line { .. } => (None, style.stroke),
+            Shape::Text { .. } => (style.fill, None),
+            _ => (style.fill, style.stroke),
+        };
+        if fill.is_none() && stroke.is_none() {
+            return;
+        }
+
+        content.push_str("q\n");
+        let state = GraphicsState::new(
+            fill.map_or(255, |color| color.alpha),
+            stroke.map_or(255, |color| color.alpha),
+            style.blend_mode,
+        );
+        if !state.is_default() {
+            let _ = writeln!(content, "/{} gs", state.name());
+            graphics_states.insert(state);
+        }
+        if let Some(fill) = fill {
+            let _ = writeln!(content, "{} rg", color_operands(fill));
+        }
+        if let Some(stroke) = stroke {
+            let _ = writeln!(content, "{} RG", color_operands(stroke));
+            self.write_stroke_style(content, style);
+        }
+
+        // points cover exactly one device pixel, whatever the transform
+        if let Shape::Point(pt) = &recorded.shape {
+            let pt = matrix.apply(pt);
+            let _ = writeln!(
+                content,
+                "{} {} 1 1 re f\nQ",
+                number(pt.x - 0.5),
+                number(pt.y - 0.5)
+            );
+            return;
+        }
+        if !matrix.is_identity() {
+            let _ = writeln!(
+                content,
+                "{} {} {} {} {} {} cm",
+                number(matrix.a),
+                number(matrix.b),
+                number(matrix.c),
+                number(matrix.d),
+                number(matrix.e),
+                number(matrix.f)
+            );
+        }
+
+        match &recorded.shape {
+            Shape::Point(_) | Shape::Image { .. } => {}
+            Shape::Line { start, end } => {
+                let _ = writeln!(
+                    content,
+                    "{} {} m {} {} l",
+                    number(start.x),
+                    number(start.y),
+                    number(end.x),
+                    number(end.y)
+                );
+            }
+            Shape::Rect(rect) => {
+                let _ = writeln!(
+                    content,
+                    "{} {} {} {} re",
+                    number(rect.x()),
+                    number(rect.y()),
+                    number(rect.width),
+                    number(rect.height)
+                );
+            }
+            Shape::Ellipse {
+                center,
+                radius_x,
+                radius_y,
+            } => {
+                let (cx, cy) = (center.x, center.y);
+                let (kx, ky) = (radius_x * KAPPA, radius_y * KAPPA);
+                let (rx, ry) = (*radius_x, *radius_y);
+                let _ = writeln!(content, "{} {} m", number(cx + rx), number(cy));
+                let quarters = [
+                    [(cx + rx, cy + ky), (cx + kx, cy + ry), (cx, cy + ry)],
+                    [(cx - kx, cy + ry), (cx - rx, cy + ky), (cx - rx, cy)],
+                    [(cx - rx, cy - ky), (cx - kx, cy - ry), (cx, cy - ry)],
+                    [(cx + kx, cy - ry), (cx + rx, cy - ky), (cx + rx, cy)],
+                ];
+                for [control1, control2, end] in quarters {
+                    let _ = writeln!(
+                        content,
+                        "{} {} {} {} {} {} c",
+                        number(control1.0),
+                        number(control1.1),
+                        number(control2.0),
+                        number(control2.1),
+                        number(end.0),
+                        number(end.1)
+                    );
+                }
+                content.push_str("h\n");
+            }
+            Shape::Path(path) => {
+                for curve in path.curves() {
+                    let _ = match curve {
+                        Curve::MoveTo(pt) => {
+                            writeln!(content, "{} {} m", number(pt.0), number(pt.1))
+                        }
+                        Curve::LineTo(pt) => {
+                            writeln!(content, "{} {} l", number(pt.0), number(pt.1))
+                        }
+                        Curve::CubicTo(control1, control2, pt) => writeln!(
+                            content,
+                            "{} {} {} {} {} {} c",
+                            number(control1.0),
+                            number(control1.1),
+                            number(control2.0),
+                            number(control2.1),
+                            number(pt.0),
+                            number(pt.1)
+                        ),
+                        Curve::Close => writeln!(content, "h"),
+                    };
+                }
+            }
+            Shape::Text {
+                runs,
+                font,
+                size,
+                weight,
+            } => {
+                content.push_str("BT\n");
+                match font {
+                    Some(font) if has_true_type_outlines(font) => {
+                        self.write_embedded_text(content, runs, font, *size, used_fonts)
+                    }
+                    _ => self.write_bitmap_text(content, runs, font, *size, *weight, used_fonts),
+                }
+                content.push_str("ET\nQ\n");
+                return;
+            }
+        }
+
+        let even_odd = style.fill_rule == FillRule::EvenOdd;
+        let paint = match (fill.is_some(), stroke.is_some()) {
+            (true, true) if even_odd => "B*",
+            (true, true) => "B",
+            (true, false) if even_odd => "f*",
+            (true, false) => "f",
+            _ => "S",
+        };
+        let _ = writeln!(content, "{}\nQ", paint);
+    }
+
+    /// Text drawn with Type3 fonts of the glyphs the canvas renders, one byte
+    /// codes index the characters of each font.
+    fn write_bitmap_text(
+        &mut self,
+        content: &mut String,
+        runs: &[TextRun],
+        font: &Option<Font>,
+        size: usize,
+        weight: FontWeight,
+        used_fonts: &mut BTreeMap<String, usize>,
+    ) {
+        let face = FontFace::new(font.as_ref(), size, weight);
+        for run in runs {
+            let natural_width: f32 = run
+                .text
+                .chars()
+                .map(|char| face.line_width(char.encode_utf8(&mut [0; 4])))
+                .sum();
+            let scaling = if natural_width > 0.0 {
+                100.0 * run.width / natural_width
+            } else {
+                100.0
+            };
+            // glyphs are drawn upright again in the flipped page
+            let _ = writeln!(
+                content,
+                "{} Tz 1 0 0 -1 {} {} Tm",
+                number(scaling),
+                number(run.position.x),
+                number(run.position.y)
+            );
+            let mut current_font = None;
+            for char in run.text.chars() {
+                let index = self.font_for(font, size, weight, char);
+                let code = self.fonts[index]
+                    .chars
+                    .iter()
+                    .position(|c| *c == char)
+                    .unwrap();
+                if current_font != Some(index) {
+                    if current_font.is_some() {
+                        content.push_str("> Tj\n");
+                    }
+                    let _ = write!(content, "/F{} 1 Tf <", index);
+                    current_font = Some(index);
+                    used_fonts.insert(format!("F{}", index), self.fonts[index].id);
+                }
+                let _ = write!(content, "{:02X}", code);
+            }
+            if current_font.is_some() {
+                content.push_str("> Tj\n");
+            }
+        }
+    }
+
+    /// Text drawn with the outlines of an embedded font, two byte codes are
+    /// glyph indices and kerning moves the glyphs like on the canvas.
+    fn write_embedded_text(
+        &mut self,
+        content: &mut String,
+        runs: &[TextRun],
+        font: &Font,
+        size: usize,
+        used_fonts: &mut BTreeMap<String, usize>,
+    ) {
+        let index = self.embedded_font_for(font);
+        used_fonts.insert(format!("E{}", index), self.embedded_fonts[index].id);
+        let face = FontFace::new(Some(font), size, FontWeight::Regular);
+        let _ = writeln!(content, "/E{} {} Tf", index, size);
+        for run in runs {
+            let natural_width = face.line_width(&run.text);
+            let scaling = if natural_width > 0.0 {
+                100.0 * run.width / natural_width
+            } else {
+                100.0
+            };
+            // outlines start on pixel edges, the page addresses pixel centers
+            let _ = writeln!(
+                content,
+                "{} Tz 1 0 0 -1 {} {} Tm",
+                number(scaling),
+                number(run.position.x - 0.5),
+                number(run.position.y - 0.5)
+            );
+            content.push_str("[<");
+            let mut previous = None;
+            for char in run.text.chars() {
+                if let Some(previous) = previous {
+                    let kerning = font.kerning(previous, char);
+                    if kerning != 0.0 {
+                        let _ = write!(content, "> {} <", number(-kerning));
+                    }
+                }
+                let glyph = font.glyph_index(char);
+                self.embedded_fonts[index]
+                    .glyphs
+                    .entry(glyph)
+                    .or_insert(char);
+                let _ = write!(content, "{:04X}", glyph);
+                previous = Some(char);
+            }
+            content.push_str(">] TJ\n");
+        }
+    }
+
+    /// Writes `image` as an RGB image with its alpha channel as soft mask,
+    /// returns the id of the image.
+    fn write_image(&mut self, image: &Image, sampling: ImageSampling) -> io::Result<usize> {
+        let pixels = image.pixelbuffer().as_raw_buffer();
+        let entries = format!(
+            " /Type /XObject /Subtype /Image /Width {} /Height {} /BitsPerComponent 8 /Interpolate {}",
+            image.width(),
+            image.height(),
+            sampling == ImageSampling::Bilinear
+        );
+
+        let mut soft_mask = String::new();
+        if pixels.chunks_exact(4).any(|pixel| pixel[3] < 255) {
+            let alpha: Vec<u8> = pixels.chunks_exact(4).map(|pixel| pixel[3]).collect();
+            let id = self.allocate();
+            self.write_stream(id, &format!("{} /ColorSpace /DeviceGray", entries), &alpha)?;
+            soft_mask = format!(" /SMask {} 0 R", id);
+        }
+        let rgb: Vec<u8> = pixels
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect();
+        let id = self.allocate();
+        self.write_stream(
+            id,
+            &format!("{} /ColorSpace /DeviceRGB{}", entries, soft_mask),
+            &rgb,
+        )?;
+        Ok(id)
+    }
+
+    fn write_stroke_style(&self, content: &mut String, style: &ShapeStyle) {
+        // the canvas draws thinner strokes one pixel wide, which is a point here
+        let scale = style.matrix.scale_factor();
+        let width = if scale > 0.0 {
+            style.stroke_weight.max(1.0 / scale)
+        } else {
+            style.stroke_weight
+        };
+        let cap = match style.stroke_cap {
+            LineCap::Butt => 0,
+            LineCap::Round => 1,
+            LineCap::Square => 2,
+        };
+        let join = match style.stroke_join {
+            LineJoin::Miter => 0,
+            LineJoin::Round => 1,
+            LineJoin::Bevel => 2,
+        };
+        let _ = writeln!(
+            content,
+            "{} w {} J {} j {} M",
+            number(width),
+            cap,
+            join,
+            number(MITER_LIMIT)
+        );
+    }
+
+    /// Glyph procedure drawing the coverage mask of `char` as a one bit
+    /// image mask, glyph space units are pixels with the origin on the baseline.
+    fn glyph_procedure(face: &FontFace, char: char) -> (f32, String) {
+        let text = char.to_string();
+        let advance = face.line_width(&text);
+        let ascent = face.ascent();
+        let margin = (face.line_height() / 4.0).ceil();
+        let mask_width = (advance + 2.0 * margin).ceil().max(1.0) as usize;
+        let mask_height = (face.line_height() + 2.0 * margin).ceil() as usize;
+        let mut mask = vec![0u8; mask_width * mask_height];
+        face.render_line(&text, &mut mask, mask_width, margin, margin);
+
+        let covered = |col: usize, row: usize| mask[row * mask_width + col] >= 128;
+        let rows: Vec<usize> = (0..mask_height)
+            .filter(|row| (0..mask_width).any(|col| covered(col, *row)))
+            .collect();
+        let cols: Vec<usize> = (0..mask_width)
+            .filter(|col| (0..mask_height).any(|row| covered(*col, row)))
+            .collect();
+        let (top, bottom, left, right) =
+            match (rows.first(), rows.last(), cols.first(), cols.last()) {
+                (Some(top), Some(bottom), Some(left), Some(right)) => {
+                    (*top, *bottom + 1, *left, *right + 1)
+                }
+                _ => return (advance, format!("{} 0 0 0 0 0 d1\n", number(advance))),
+            };
+
+        let (width, height) = (right - left, bottom - top);
+        // mask pixels are centered on the positions the canvas plots them at
+        let x = left as f32 - margin - 0.5;
+        let y = ascent + margin - bottom as f32 + 0.5;
+        let mut bits = Vec::with_capacity(height * width.div_ceil(8));
+        for row in top..bottom {
+            for byte_start in (left..right).step_by(8) {
+                let mut byte = 0u8;
+                for bit in 0..8 {
+                    let col = byte_start + bit;
+                    if col < right && covered(col, row) {
+                        byte |= 0x80 >> bit;
+                    }
+                }
+                bits.push(byte);
+            }
+        }
+        let procedure = format!(
+            "{} 0 {} {} {} {} d1\nq {} 0 0 {} {} {} cm\nBI /IM true /W {} /H {} /BPC 1 /D [1 0] /F /AHx ID\n{}>\nEI\nQ\n",
+            number(advance),
+            number(x),
+            number(y),
+            number(x + width as f32),
+            number(y + height as f32),
+            width,
+            height,
+            number(x),
+            number(y),
+            width,
+            height,
+            hex(&bits)
+        );
+        (advance, procedure)
+    }
+
+    fn write_fonts(&mut self) -> io::Result<()> {
+        let fonts = std::mem::take(&mut self.fonts);
+        for font in &fonts {
+            let face = FontFace::new(font.font.as_ref(), font.size, font.weight);
+            let mut widths = Vec::with_capacity(font.chars.len());
+            let mut char_procs = String::new();
+            let mut differences = String::new();
+            for (code, char) in font.chars.iter().enumerate() {
+                let (advance, procedure) = Self::glyph_procedure(&face, *char);
+                let procedure_id = self.allocate();
+                self.write_stream(procedure_id, "", procedure.as_bytes())?;
+                widths.push(number(advance));
+                let _ = write!(char_procs, " /g{} {} 0 R", code, procedure_id);
+                let _ = write!(differences, " /g{}", code);
+            }
+
+            let codes: Vec<(usize, char)> = font.chars.iter().copied().enumerate().collect();
+            let cmap = to_unicode(1, &codes);
+            let cmap_id = self.allocate();
+            self.write_stream(cmap_id, "", cmap.as_bytes())?;
+
+            self.write_object(
+                font.id,
+                &format!(
+                    "<< /Type /Font /Subtype /Type3 /FontBBox [0 0 0 0] /FontMatrix [1 0 0 1 0 0] \
+                     /CharProcs <<{}>> /Encoding << /Type /Encoding /Differences [0{}] >> \
+                     /FirstChar 0 /LastChar {} /Widths [{}] /Resources << >> /ToUnicode {} 0 R >>",
+                    char_procs,
+                    differences,
+                    font.chars.len() - 1,
+                    widths.join(" "),
+                    cmap_id
+                ),
+            )?;
+        }
+
+        let embedded_fonts = std::mem::take(&mut self.embedded_fonts);
+        for (index, embedded) in embedded_fonts.iter().enumerate() {
+            let font = &embedded.font;
+            let file_id = self.allocate();
+            let data = font.data();
+            self.write_stream(file_id, &format!(" /Length1 {}", data.len()), data)?;
+
+            let (ascent, descent) = font.line_metrics();
+            let mut bounds = [0.0, descent, 0.0, ascent];
+            let mut widths = String::new();
+            for glyph in embedded.glyphs.keys() {
+                let (advance, glyph_bounds) = font.glyph_metrics(*glyph);
+                let _ = write!(widths, " {} [{}]", glyph, number(advance));
+                for corner in 0..2 {
+                    bounds[corner] = bounds[corner].min(glyph_bounds[corner]);
+                    bounds[corner + 2] = bounds[corner + 2].max(glyph_bounds[corner + 2]);
+                }
+            }
+            let name: String = font
+                .name()
+                .unwrap_or_default()
+                .chars()
+                .filter(|char| char.is_ascii_alphanumeric() || *char == '-')
+                .collect();
+            let name = if name.is_empty() {
+                format!("Embedded{}", index)
+            } else {
+                name
+            };
+
+            let descriptor_id = self.allocate();
+            self.write_object(
+                descriptor_id,
+                &format!(
+                    "<< /Type /FontDescriptor /FontName /{} /Flags 4 /FontBBox [{}] \
+                     /ItalicAngle 0 /Ascent {} /Descent {} /CapHeight {} /StemV 80 \
+                     /FontFile2 {} 0 R >>",
+                    name,
+                    bounds.map(number).join(" "),
+                    number(ascent),
+                    number(descent),
+                    number(ascent),
+                    file_id
+                ),
+            )?;
+            let cid_font_id = self.allocate();
+            self.write_object(
+                cid_font_id,
+                &format!(
+                    "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{} \
+                     /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> \
+                     /FontDescriptor {} 0 R /CIDToGIDMap /Identity /W [{}] >>",
+                    name, descriptor_id, widths
+                ),
+            )?;
+            let codes: Vec<(usize, char)> = embedded
+                .glyphs
+                .iter()
+                .map(|(glyph, char)| (*glyph as usize, *char))
+                .collect();
+            let cmap_id = self.allocate();
+            self.write_stream(cmap_id, "", to_unicode(2, &codes).as_bytes())?;
+            self.write_object(
+                embedded.id,
+                &format!(
+                    "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H \
+                     /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+                    name, cid_font_id, cmap_id
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes the fonts, page tree and cross reference table.
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        self.write_fonts()?;
+        let kids: Vec<String> = self
+            .page_ids
+            .iter()
+            .map(|id| format!("{} 0 R", id))
+            .collect();
+        self.write_object(
+            PAGES_ID,
+            &format!(
+                "<< /Type /Pages /Kids [{}] /Count {} >>",
+                kids.join(" "),
+                self.page_ids.len()
+            ),
+        )?;
+        self.write_object(
+            CATALOG_ID,
+            &format!("<< /Type /Catalog /Pages {} 0 R >>", PAGES_ID),
+        )?;
+
+        let xref_offset = self.offset;
+        let mut xref = format!(
+            "xref\n0 {}\n0000000000 65535 f \n",
+            self.object_offsets.len() + 1
+        );
+        for offset in &self.object_offsets {
+            let _ = writeln!(xref, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            xref,
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.object_offsets.len() + 1,
+            CATALOG_ID,
+            xref_offset
+        );
+        self.write(xref.as_bytes())?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::{Canvas, FloatPoint, FloatRect};
+    use crate::display_list::Recording;
+
+    fn page(color: Color) -> DisplayList {
+        let mut canvas = Canvas::new(20, 10);
+        canvas.begin_recording(Recording::VectorOnly);
+        canvas.fill(color);
+        canvas.draw_ellipse(FloatPoint::new(10.0, 5.0), 4.0, 3.0);
+        canvas.display_list().unwrap().clone()
+    }
+
+    fn document(pages: &[DisplayList]) -> Vec<u8> {
+        let mut document = PdfDocument::new(Vec::new()).unwrap();
+        for display_list in pages {
+            document.add_page(display_list).unwrap();
+        }
+        document.finish().unwrap()
+    }
+
+    #[test]
+    fn xref_points_at_objects() {
+        let pdf = document(&[
+            page(Color::rgb(255, 0, 0)),
+            page(Color::rgba(0, 0, 255, 128)),
+        ]);
+
+        let text = String::from_utf8_lossy(&pdf);
+        let startxref = text.rfind("startxref\n").unwrap() + "startxref\n".len();
+        let xref_offset: usize = text[startxref..].lines().next().unwrap().parse().unwrap();
+        let mut lines = std::str::from_utf8(&pdf[xref_offset..]).unwrap().lines();
+        assert_eq!(lines.next(), Some("xref"));
+        assert_eq!(lines.next(), Some("0 7"));
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+        for id in 1..7 {
+            let entry = lines.next().unwrap();
+            assert!(entry.ends_with(" 00000 n "));
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", id).as_bytes()));
+        }
+        assert_eq!(lines.next(), Some("trailer"));
+        assert_eq!(lines.next(), Some("<< /Size 7 /Root 1 0 R >>"));
+    }
+
+    #[test]
+    fn objects_are_counted_once() {
+        let pdf = document(&[page(Color::rgb(255, 0, 0)), page(Color::rgb(0, 255, 0))]);
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert_eq!(pdf.matches(" 0 obj\n").count(), 6);
+        assert_eq!(pdf.matches("endobj\n").count(), 6);
+        assert!(pdf.contains("/Count 2"));
+        assert_eq!(pdf.matches("/Type /Page ").count(), 2);
+    }
+
+    #[test]
+    fn translucent_images_get_a_soft_mask() {
+        let mut opaque = Image::new(2, 1);
+        opaque.set_pixel(0, 0, Color::rgb(255, 0, 0));
+        let mut translucent = Image::new(1, 1);
+        translucent.set_pixel(0, 0, Color::rgba(0, 0, 255, 128));
+
+        let mut canvas = Canvas::new(20, 10);
+        canvas.begin_recording(Recording::VectorOnly);
+        canvas.draw_image(
+            &opaque,
+            FloatRect::new(FloatPoint::new(0.0, 0.0), 4.0, 2.0),
+            None,
+        );
+        canvas.draw_image(
+            &translucent,
+            FloatRect::new(FloatPoint::new(4.0, 0.0), 1.0, 1.0),
+            None,
+        );
+        let pdf = document(&[canvas.display_list().unwrap().clone()]);
+        let rgb = b"/DeviceRGB >>\nstream\n\xff\x00\x00\xff\xff\xff\nendstream";
+        assert!(pdf.windows(rgb.len()).any(|window| window == rgb));
+        let pdf = String::from_utf8_lossy(&pdf);
+
+        assert!(pdf.contains(
+            "/Width 2 /Height 1 /BitsPerComponent 8 /Interpolate false /ColorSpace /DeviceRGB >>"
+        ));
+        assert!(pdf.contains(
+            "/Width 1 /Height 1 /BitsPerComponent 8 /Interpolate false /ColorSpace /DeviceGray >>"
+        ));
+        assert!(pdf.contains("/ColorSpace /DeviceRGB /SMask 5 0 R >>"));
+        assert!(pdf.contains("/XObject << /Im0 4 0 R /Im1 6 0 R >>"));
+        assert!(pdf.contains("q\n1 0 0 1 -0.5 -0.5 cm\n4 0 0 -2 0 2 cm\n/Im0 Do\nQ"));
+    }
+
+    #[test]
+    fn thin_strokes_stay_one_pixel_wide() {
+        let line_width = |weight, scale| {
+            let mut canvas = Canvas::new(20, 10);
+            canvas.begin_recording(Recording::VectorOnly);
+            canvas.stroke(Color::rgb(0, 0, 0));
+            canvas.stroke_weight(weight);
+            canvas.scale(scale, scale);
+            canvas.draw_line((1.0, 1.0), (4.0, 2.0));
+            let pdf = document(&[canvas.display_list().unwrap().clone()]);
+            let pdf = String::from_utf8_lossy(&pdf);
+            let end = pdf.find(" w ").unwrap();
+            let start = pdf[..end].rfind('\n').unwrap() + 1;
+            pdf[start..end].parse::<f32>().unwrap()
+        };
+        assert_eq!(line_width(3.0, 1.0), 3.0);
+        assert_eq!(line_width(0.5, 1.0), 1.0);
+        assert_eq!(line_width(0.5, 4.0), 0.5);
+        assert_eq!(line_width(0.1, 4.0), 0.25);
+    }
+
+    #[test]
+    fn blend_modes_get_graphics_states() {
+        let mut canvas = Canvas::new(20, 10);
+        canvas.begin_recording(Recording::VectorOnly);
+        canvas.fill(Color::rgba(255, 0, 0, 128));
+        canvas.blend_mode(BlendMode::Difference);
+        canvas.draw_rect(FloatRect::new(FloatPoint::new(0.0, 0.0), 4.0, 4.0));
+        canvas.blend_mode(BlendMode::Add);
+        canvas.draw_image(
+            &Image::new(1, 1),
+            FloatRect::new(FloatPoint::new(0.0, 0.0), 4.0, 4.0),
+            None,
+        );
+        let pdf = document(&[canvas.display_list().unwrap().clone()]);
+        let pdf = String::from_utf8_lossy(&pdf);
+
+        assert!(pdf.contains("q\n/GS128_255_Difference gs\n"));
+        assert!(pdf.contains("q\n/GS255_255_Screen gs\n1 0 0 1 -0.5 -0.5 cm\n"));
+        assert!(pdf.contains(
+            "/ExtGState << /GS128_255_Difference << /ca 0.502 /CA 1 /BM /Difference >> \
+             /GS255_255_Screen << /ca 1 /CA 1 /BM /Screen >> >>"
+        ));
+    }
+
+    #[test]
+    fn to_unicode_maps_codes_of_either_width() {
+        let one_byte = to_unicode(1, &[(0, 'a'), (1, '\u{1F600}')]);
+        assert!(one_byte.contains("<00> <FF>\nendcodespacerange"));
+        assert!(one_byte.contains("2 beginbfchar\n<00> <0061>\n<01> <D83DDE00>\nendbfchar"));
+
+        let codes: Vec<(usize, char)> = (0..150).map(|code| (code * 3, 'x')).collect();
+        let two_bytes = to_unicode(2, &codes);
+        assert!(two_bytes.contains("<0000> <FFFF>\nendcodespacerange"));
+        assert!(two_bytes.contains("100 beginbfchar\n<0000> <0078>"));
+        assert!(two_bytes.contains("50 beginbfchar\n<012C> <0078>"));
+        assert!(two_bytes.contains("<01BF> <0078>\nendbfchar\nendcmap"));
+    }
+}
@@ -1,5 +1,6 @@
-use crate::canvas::Color;
+use crate::canvas::{BlendMode, Color};
 
+#[derive(PartialEq)]
 pub struct PixelBuffer {
     buffer: Vec<Color>,
     width: usize,
@@ -33,15 +34,36 @@ impl PixelBuffer {
         height * self.width + width
     }
 
+    fn checked_index(&self, x: isize, y: isize) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return None;
+        }
+        Some(self.cartesian_to_index(x as usize, y as usize))
+    }
+
     pub fn set_pixel(&mut self, x: isize, y: isize, color: &Color) {
-        if x < 0 || y < 0 {
-            return;
+        if let Some(index) = self.checked_index(x, y) {
+            self.buffer[index] = *color;
         }
-        let index = self.cartesian_to_index(x as usize, y as usize);
-        if index >= self.buffer.len() {
-            return;
+    }
+
+    /// Composites `color` onto the existing pixel, `coverage` being the
+    /// fraction of the pixel covered by the shape in the range 0.0 to 1.0.
+    pub fn blend_pixel(
+        &mut self,
+        x: isize,
+        y: isize,
+        color: &Color,
+        coverage: f32,
+        mode: BlendMode,
+    ) {
+        if let Some(index) = self.checked_index(x, y) {
+            if mode == BlendMode::SourceOver && color.is_opaque() && coverage >= 1.0 {
+                self.buffer[index] = *color;
+            } else {
+                self.buffer[index] = color.composite_over(&self.buffer[index], mode, coverage);
+            }
         }
-        self.buffer[index] = *color;
     }
 
     pub fn at(&self, x: usize, y: usize) -> Option<&Color> {
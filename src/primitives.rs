@@ -1,44 +1,152 @@
-#[derive(Debug, Clone)]
-pub struct IntPoint {
-    pub x: isize,
-    pub y: isize,
+use crate::vector::Vec2;
+use std::ops::{Add, Neg};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Point<T> {
+    pub x: T,
+    pub y: T,
+}
+
+pub type IntPoint = Point<isize>;
+pub type FloatPoint = Point<f32>;
+
+impl<T> Point<T> {
+    pub fn new(x: T, y: T) -> Self {
+        Point { x, y }
+    }
 }
 
-impl IntPoint {
-    pub fn new(x: isize, y: isize) -> Self {
-        IntPoint { x, y }
+impl FloatPoint {
+    pub fn round(&self) -> IntPoint {
+        IntPoint::new(self.x.round() as isize, self.y.round() as isize)
+    }
+
+    pub fn lerp_to(&self, to: &FloatPoint, amount: f32) -> FloatPoint {
+        FloatPoint::new(
+            self.x + (to.x - self.x) * amount,
+            self.y + (to.y - self.y) * amount,
+        )
+    }
+
+    pub fn distance(&self, to: &FloatPoint) -> f32 {
+        ((to.x - self.x).powi(2) + (to.y - self.y).powi(2)).sqrt()
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct IntRect {
-    pub location: IntPoint,
-    pub width: isize,
-    pub height: isize,
+impl From<IntPoint> for FloatPoint {
+    fn from(pt: IntPoint) -> Self {
+        FloatPoint::new(pt.x as f32, pt.y as f32)
+    }
 }
 
-impl IntRect {
-    pub fn new(mut location: IntPoint, mut width: isize, mut height: isize) -> Self {
-        if width < 0 {
-            location.x += width;
-            width *= -1;
+impl From<&IntPoint> for FloatPoint {
+    fn from(pt: &IntPoint) -> Self {
+        FloatPoint::from(*pt)
+    }
+}
+
+impl From<&FloatPoint> for FloatPoint {
+    fn from(pt: &FloatPoint) -> Self {
+        *pt
+    }
+}
+
+impl From<Vec2> for FloatPoint {
+    fn from(v: Vec2) -> Self {
+        FloatPoint::new(v.x, v.y)
+    }
+}
+
+impl From<&Vec2> for FloatPoint {
+    fn from(v: &Vec2) -> Self {
+        FloatPoint::new(v.x, v.y)
+    }
+}
+
+impl From<(f32, f32)> for FloatPoint {
+    fn from((x, y): (f32, f32)) -> Self {
+        FloatPoint::new(x, y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect<T> {
+    pub location: Point<T>,
+    pub width: T,
+    pub height: T,
+}
+
+pub type IntRect = Rect<isize>;
+pub type FloatRect = Rect<f32>;
+
+impl<T> Rect<T>
+where
+    T: Copy + Default + PartialOrd + Add<Output = T> + Neg<Output = T>,
+{
+    pub fn new(mut location: Point<T>, mut width: T, mut height: T) -> Self {
+        if width < T::default() {
+            location.x = location.x + width;
+            width = -width;
         }
-        if height < 0 {
-            location.y += height;
-            height *= -1;
+        if height < T::default() {
+            location.y = location.y + height;
+            height = -height;
         }
-        IntRect {
+        Rect {
             location,
             width,
             height,
         }
     }
 
-    pub fn x(&self) -> isize {
+    pub fn x(&self) -> T {
         self.location.x
     }
 
-    pub fn y(&self) -> isize {
+    pub fn y(&self) -> T {
         self.location.y
     }
+
+    pub fn contains(&self, pt: &Point<T>) -> bool {
+        pt.x >= self.x()
+            && pt.y >= self.y()
+            && pt.x <= self.x() + self.width
+            && pt.y <= self.y() + self.height
+    }
+}
+
+impl FloatRect {
+    pub fn round(&self) -> IntRect {
+        IntRect::new(
+            self.location.round(),
+            self.width.round() as isize,
+            self.height.round() as isize,
+        )
+    }
+
+    pub fn center(&self) -> FloatPoint {
+        FloatPoint::new(self.x() + self.width / 2.0, self.y() + self.height / 2.0)
+    }
+}
+
+impl From<IntRect> for FloatRect {
+    fn from(rect: IntRect) -> Self {
+        FloatRect {
+            location: rect.location.into(),
+            width: rect.width as f32,
+            height: rect.height as f32,
+        }
+    }
+}
+
+impl From<&IntRect> for FloatRect {
+    fn from(rect: &IntRect) -> Self {
+        FloatRect::from(*rect)
+    }
+}
+
+impl From<&FloatRect> for FloatRect {
+    fn from(rect: &FloatRect) -> Self {
+        *rect
+    }
 }
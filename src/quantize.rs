@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+
+/// Bits per channel colors are bucketed to before the median cut, which
+/// keeps the cost of building a palette independent of the image size.
+const HISTOGRAM_BITS: u32 = 5;
+
+#[derive(Clone, Copy, Default)]
+struct Bucket {
+    count: u64,
+    sum: [u64; 3],
+}
+
+impl Bucket {
+    fn mean(&self) -> [u8; 3] {
+        let count = self.count.max(1);
+        [
+            ((self.sum[0] + count / 2) / count) as u8,
+            ((self.sum[1] + count / 2) / count) as u8,
+            ((self.sum[2] + count / 2) / count) as u8,
+        ]
+    }
+}
+
+/// Color occurrences of one or more RGBA images, alpha is ignored.
+pub(crate) struct Histogram {
+    buckets: Vec<Bucket>,
+}
+
+impl Histogram {
+    pub(crate) fn new() -> Self {
+        Histogram {
+            buckets: vec![Bucket::default(); 1 << (3 * HISTOGRAM_BITS)],
+        }
+    }
+
+    pub(crate) fn add(&mut self, rgba: &[u8]) {
+        let shift = 8 - HISTOGRAM_BITS;
+        for pixel in rgba.chunks_exact(4) {
+            let index = ((pixel[0] as usize >> shift) << (2 * HISTOGRAM_BITS))
+                | ((pixel[1] as usize >> shift) << HISTOGRAM_BITS)
+                | (pixel[2] as usize >> shift);
+            let bucket = &mut self.buckets[index];
+            bucket.count += 1;
+            for (sum, value) in bucket.sum.iter_mut().zip(pixel) {
+                *sum += *value as u64;
+            }
+        }
+    }
+
+    /// Median cut: starting from a box holding every color, the box with the
+    /// largest population weighted extent is split at the median of its
+    /// widest channel until `max_colors` boxes exist. Each box contributes
+    /// the average of its colors to the palette.
+    pub(crate) fn palette(&self, max_colors: usize) -> Palette {
+        let mut buckets: Vec<Bucket> = self
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.count > 0)
+            .copied()
+            .collect();
+        if buckets.is_empty() {
+            return Palette::new(vec![[0, 0, 0]]);
+        }
+
+        let mut boxes = Vec::with_capacity(max_colors);
+        boxes.push(0..buckets.len());
+        while boxes.len() < max_colors {
+            let candidate = boxes
+                .iter()
+                .enumerate()
+                .filter(|(_, range)| range.len() > 1)
+                .map(|(index, range)| {
+                    let (channel, extent) = widest_channel(&buckets[range.clone()]);
+                    let population: u64 = buckets[range.clone()].iter().map(|b| b.count).sum();
+                    (index, channel, extent as u64 * population)
+                })
+                .max_by_key(|(_, _, score)| *score);
+            let (index, channel, _) = match candidate {
+                Some(candidate) => candidate,
+                None => break,
+            };
+
+            let range = boxes[index].clone();
+            let slice = &mut buckets[range.clone()];
+            slice.sort_by_key(|bucket| bucket.mean()[channel]);
+            let half = slice.iter().map(|bucket| bucket.count).sum::<u64>() / 2;
+            let mut seen = 0;
+            let mut split = 1;
+            for (position, bucket) in slice.iter().enumerate() {
+                seen += bucket.count;
+                if seen >= half {
+                    split = position + 1;
+                    break;
+                }
+            }
+            let split = range.start + split.clamp(1, slice.len() - 1);
+            boxes[index] = range.start..split;
+            boxes.push(split..range.end);
+        }
+
+        let colors = boxes
+            .iter()
+            .map(|range| {
+                let mut total = Bucket::default();
+                for bucket in &buckets[range.clone()] {
+                    total.count += bucket.count;
+                    for channel in 0..3 {
+                        total.sum[channel] += bucket.sum[channel];
+                    }
+                }
+                total.mean()
+            })
+            .collect();
+        Palette::new(colors)
+    }
+}
+
+fn widest_channel(buckets: &[Bucket]) -> (usize, u8) {
+    let mut min = [u8::MAX; 3];
+    let mut max = [u8::MIN; 3];
+    for bucket in buckets {
+        let mean = bucket.mean();
+        for channel in 0..3 {
+            min[channel] = min[channel].min(mean[channel]);
+            max[channel] = max[channel].max(mean[channel]);
+        }
+    }
+    (0..3)
+        .map(|channel| (channel, max[channel] - min[channel]))
+        .max_by_key(|(_, extent)| *extent)
+        .unwrap()
+}
+
+/// Up to 256 colors together with a cache of nearest color lookups.
+pub(crate) struct Palette {
+    colors: Vec<[u8; 3]>,
+    lookup: HashMap<[u8; 3], u8>,
+}
+
+impl Palette {
+    fn new(colors: Vec<[u8; 3]>) -> Self {
+        Palette {
+            colors,
+            lookup: HashMap::new(),
+        }
+    }
+
+    /// Flat `[r, g, b, r, g, b, ...]` table.
+    pub(crate) fn to_rgb(&self) -> Vec<u8> {
+        self.colors.iter().flatten().copied().collect()
+    }
+
+    /// Index of the closest palette color, remembered per exact color since
+    /// images usually repeat few of them.
+    fn nearest(&mut self, color: [u8; 3]) -> u8 {
+        let colors = &self.colors;
+        *self.lookup.entry(color).or_insert_with(|| {
+            let (index, _) = colors
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, candidate)| {
+                    (0..3)
+                        .map(|channel| {
+                            let difference = candidate[channel] as i32 - color[channel] as i32;
+                            difference * difference
+                        })
+                        .sum::<i32>()
+                })
+                .unwrap();
+            index as u8
+        })
+    }
+
+    /// Maps an RGBA image to palette indices, optionally spreading the
+    /// quantization error onto the neighbouring pixels with Floyd–Steinberg
+    /// dithering.
+    pub(crate) fn index(&mut self, rgba: &[u8], width: usize, dither: bool) -> Vec<u8> {
+        if width == 0 || rgba.is_empty() {
+            return Vec::new();
+        }
+        if !dither {
+            return rgba
+                .chunks_exact(4)
+                .map(|pixel| self.nearest([pixel[0], pixel[1], pixel[2]]))
+                .collect();
+        }
+
+        // errors in sixteenths, padded by one pixel on both sides
+        let mut current = vec![[0i32; 3]; width + 2];
+        let mut next = vec![[0i32; 3]; width + 2];
+        let mut indices = Vec::with_capacity(rgba.len() / 4);
+        for row in rgba.chunks_exact(width * 4) {
+            for (x, pixel) in row.chunks_exact(4).enumerate() {
+                let mut wanted = [0u8; 3];
+                for channel in 0..3 {
+                    let value = pixel[channel] as i32 + current[x + 1][channel] / 16;
+                    wanted[channel] = value.clamp(0, 255) as u8;
+                }
+                let index = self.nearest(wanted);
+                let chosen = self.colors[index as usize];
+                for channel in 0..3 {
+                    let error = wanted[channel] as i32 - chosen[channel] as i32;
+                    current[x + 2][channel] += error * 7;
+                    next[x][channel] += error * 3;
+                    next[x + 1][channel] += error * 5;
+                    next[x + 2][channel] += error;
+                }
+                indices.push(index);
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.iter_mut().for_each(|error| *error = [0; 3]);
+        }
+        indices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(colors: &[[u8; 3]], repeat: usize) -> Vec<u8> {
+        colors
+            .iter()
+            .cycle()
+            .take(colors.len() * repeat)
+            .flat_map(|[red, green, blue]| [*red, *green, *blue, 255])
+            .collect()
+    }
+
+    /// Colors every channel of which is a multiple of eight, so each one gets
+    /// a histogram bucket of its own.
+    fn distinct_colors(count: usize) -> Vec<[u8; 3]> {
+        (0..count)
+            .map(|index| {
+                [
+                    (index % 32 * 8) as u8,
+                    (index / 32 % 32 * 8) as u8,
+                    (index * 7 % 32 * 8) as u8,
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn palette_size_is_limited() {
+        let mut histogram = Histogram::new();
+        histogram.add(&image(&distinct_colors(1000), 2));
+        for max_colors in [2, 16, 256] {
+            let palette = histogram.palette(max_colors);
+            assert_eq!(palette.to_rgb().len(), max_colors * 3);
+        }
+    }
+
+    #[test]
+    fn exact_palettes_round_trip() {
+        let colors = distinct_colors(200);
+        let rgba = image(&colors, 3);
+        let mut histogram = Histogram::new();
+        histogram.add(&rgba);
+        let mut palette = histogram.palette(256);
+        assert_eq!(palette.to_rgb().len(), colors.len() * 3);
+
+        for dither in [false, true] {
+            let indices = palette.index(&rgba, colors.len(), dither);
+            let rgb = palette.to_rgb();
+            let restored: Vec<u8> = indices
+                .iter()
+                .flat_map(|index| {
+                    let start = *index as usize * 3;
+                    [rgb[start], rgb[start + 1], rgb[start + 2], 255]
+                })
+                .collect();
+            assert_eq!(restored, rgba);
+        }
+    }
+
+    #[test]
+    fn empty_input() {
+        let mut palette = Histogram::new().palette(256);
+        assert_eq!(palette.to_rgb(), vec![0, 0, 0]);
+        assert!(palette.index(&[], 0, true).is_empty());
+        assert!(palette.index(&[], 0, false).is_empty());
+    }
+
+    #[test]
+    fn close_palette_colors_stay_apart() {
+        let mut palette = Palette::new(vec![[0, 0, 0], [3, 3, 3], [9, 0, 0]]);
+        let rgba = image(&[[0, 0, 0], [3, 3, 3], [2, 2, 2], [1, 0, 0], [7, 1, 0]], 1);
+        assert_eq!(palette.index(&rgba, 5, false), vec![0, 1, 1, 0, 2]);
+    }
+}
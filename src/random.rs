@@ -0,0 +1,163 @@
+use crate::primitives::{FloatPoint, FloatRect};
+use rand::distributions::uniform::{SampleRange, SampleUniform};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::cell::{Cell, RefCell};
+use std::f32::consts::PI;
+
+/// Random number generator owned by the sketch. It is reseeded at the start
+/// of every frame from the sketch seed and the frame number, so any frame can
+/// be rendered again exactly by knowing the seed. ChaCha8 keeps the
+/// sequences the same across platforms and `rand` releases.
+pub struct Random {
+    seed: Cell<u64>,
+    frame_seed: Cell<u64>,
+    rng: RefCell<ChaCha8Rng>,
+}
+
+impl Default for Random {
+    /// Seeded from the operating system, query `seed` to reproduce the run.
+    fn default() -> Self {
+        Random::new(rand::random())
+    }
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Random {
+            seed: Cell::new(seed),
+            frame_seed: Cell::new(seed),
+            rng: RefCell::new(ChaCha8Rng::seed_from_u64(seed)),
+        }
+    }
+
+    /// The seed every frame seed is derived from.
+    pub fn seed(&self) -> u64 {
+        self.seed.get()
+    }
+
+    /// The seed the generator was last reset to.
+    pub fn frame_seed(&self) -> u64 {
+        self.frame_seed.get()
+    }
+
+    pub(crate) fn reseed(&self, seed: u64) {
+        self.seed.set(seed);
+        self.reset(seed);
+    }
+
+    pub(crate) fn reseed_for_frame(&self, frame: usize) {
+        // mix both values so neighbouring seeds do not share frame sequences
+        let mut mixed = self.seed() ^ (frame as u64).wrapping_mul(0x9E3779B97F4A7C15);
+        mixed = (mixed ^ (mixed >> 31)).wrapping_mul(0xBF58476D1CE4E5B9);
+        self.reset(mixed ^ (mixed >> 29));
+    }
+
+    fn reset(&self, seed: u64) {
+        self.frame_seed.set(seed);
+        *self.rng.borrow_mut() = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    /// Uniformly distributed value in `range`, e.g. `random(0.0..1.0)` or `random(1..=6)`.
+    pub fn random<T: SampleUniform, R: SampleRange<T>>(&self, range: R) -> T {
+        self.rng.borrow_mut().gen_range(range)
+    }
+
+    /// Normally distributed value using the Box-Muller transform.
+    pub fn random_gaussian(&self, mean: f32, standard_deviation: f32) -> f32 {
+        let mut rng = self.rng.borrow_mut();
+        let u1: f32 = 1.0 - rng.gen::<f32>();
+        let u2: f32 = rng.gen();
+        mean + standard_deviation * (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+    }
+
+    /// Picks one of `items`, `None` if it is empty.
+    pub fn random_choice<'a, T>(&self, items: &'a [T]) -> Option<&'a T> {
+        items.choose(&mut *self.rng.borrow_mut())
+    }
+
+    pub fn shuffle<T>(&self, items: &mut [T]) {
+        items.shuffle(&mut *self.rng.borrow_mut());
+    }
+
+    pub fn random_point_in_rect(&self, rect: &FloatRect) -> FloatPoint {
+        let mut rng = self.rng.borrow_mut();
+        FloatPoint::new(
+            rect.x() + rng.gen::<f32>() * rect.width,
+            rect.y() + rng.gen::<f32>() * rect.height,
+        )
+    }
+
+    /// Uniformly distributed over the area of the circle.
+    pub fn random_point_in_circle(&self, center: &FloatPoint, radius: f32) -> FloatPoint {
+        let mut rng = self.rng.borrow_mut();
+        let distance = radius * rng.gen::<f32>().sqrt();
+        let angle = rng.gen::<f32>() * 2.0 * PI;
+        FloatPoint::new(
+            center.x + distance * angle.cos(),
+            center.y + distance * angle.sin(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(random: &Random) -> Vec<u32> {
+        (0..8).map(|_| random.random(0..1_000_000)).collect()
+    }
+
+    #[test]
+    fn same_seed_and_frame_give_the_same_sequence() {
+        let first = Random::new(7);
+        let second = Random::new(7);
+        first.reseed_for_frame(12);
+        let expected = sequence(&first);
+        second.reseed_for_frame(12);
+        assert_eq!(sequence(&second), expected);
+        assert_eq!(first.frame_seed(), second.frame_seed());
+
+        first.reseed_for_frame(13);
+        assert_ne!(sequence(&first), expected);
+        first.reseed_for_frame(12);
+        assert_eq!(sequence(&first), expected);
+        assert_eq!(first.seed(), 7);
+    }
+
+    #[test]
+    fn sequences_are_stable() {
+        let random = Random::new(42);
+        assert_eq!(
+            sequence(&random),
+            [224080, 681896, 146386, 950275, 772313, 427516, 344091, 627360]
+        );
+    }
+
+    #[test]
+    fn neighbouring_seeds_differ() {
+        let first = Random::new(1);
+        let second = Random::new(2);
+        first.reseed_for_frame(0);
+        second.reseed_for_frame(0);
+        assert_ne!(sequence(&first), sequence(&second));
+    }
+
+    #[test]
+    fn helpers_stay_in_range() {
+        let random = Random::new(3);
+        let rect = FloatRect::new(FloatPoint::new(10.0, 20.0), 5.0, 2.0);
+        let center = FloatPoint::new(-3.0, 4.0);
+        for _ in 0..1000 {
+            assert!(rect.contains(&random.random_point_in_rect(&rect)));
+            let point = random.random_point_in_circle(&center, 2.0);
+            assert!(point.distance(&center) <= 2.0 + 1e-5);
+        }
+        assert_eq!(random.random_choice::<u8>(&[]), None);
+        let mut items = [1, 2, 3, 4, 5];
+        random.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, [1, 2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::path::FillRule;
+
+const SUBSAMPLES: usize = 4;
+
+struct Edge {
+    x_top: f32,
+    y_top: f32,
+    y_bottom: f32,
+    slope: f32,
+    winding: i32,
+}
+
+impl Edge {
+    fn new(start: (f32, f32), end: (f32, f32)) -> Option<Self> {
+        if start.1 == end.1 || !start.1.is_finite() || !end.1.is_finite() {
+            return None;
+        }
+        let (top, bottom, winding) = if start.1 < end.1 {
+            (start, end, 1)
+        } else {
+            (end, start, -1)
+        };
+        Some(Edge {
+            x_top: top.0,
+            y_top: top.1,
+            y_bottom: bottom.1,
+            slope: (bottom.0 - top.0) / (bottom.1 - top.1),
+            winding,
+        })
+    }
+
+    fn x_at(&self, y: f32) -> f32 {
+        self.x_top + (y - self.y_top) * self.slope
+    }
+}
+
+/// Scanline polygon filler, every contour is implicitly closed and the
+/// winding numbers of all contours add up before `rule` decides whether a
+/// span is inside, so contours can cut holes into each other. Integer
+/// coordinates address pixel centers, the same as in the line algorithms.
+/// Calls `plot` once per touched pixel with the covered fraction of that
+/// pixel, which is either 1.0 or, when `anti_alias` is set, estimated from
+/// several sub-scanlines.
+pub(crate) fn fill_contours<F: FnMut(isize, isize, f32)>(
+    contours: &[Vec<(f32, f32)>],
+    rule: FillRule,
+    width: usize,
+    height: usize,
+    anti_alias: bool,
+    mut plot: F,
+) {
+    let mut edges = Vec::new();
+    for contour in contours {
+        for (index, start) in contour.iter().enumerate() {
+            let end = contour[(index + 1) % contour.len()];
+            let start = (start.0 + 0.5, start.1 + 0.5);
+            let end = (end.0 + 0.5, end.1 + 0.5);
+            if let Some(edge) = Edge::new(start, end) {
+                edges.push(edge);
+            }
+        }
+    }
+    if edges.is_empty() || width == 0 || height == 0 {
+        return;
+    }
+    edges.sort_by(|a, b| a.y_top.total_cmp(&b.y_top));
+
+    let y_min = edges[0].y_top.floor().max(0.0) as usize;
+    let y_max = edges
+        .iter()
+        .map(|edge| edge.y_bottom)
+        .fold(f32::MIN, f32::max)
+        .ceil()
+        .min(height as f32) as usize;
+
+    let samples = if anti_alias { SUBSAMPLES } else { 1 };
+    let sample_weight = 1.0 / samples as f32;
+    let mut coverage = vec![0.0f32; width];
+    let mut active: Vec<usize> = Vec::new();
+    let mut crossings: Vec<(f32, i32)> = Vec::new();
+    let mut next_edge = 0;
+
+    for y in y_min..y_max {
+        let mut touched = (width, 0);
+
+        for sample in 0..samples {
+            let sample_y = y as f32 + (sample as f32 + 0.5) * sample_weight;
+
+            while next_edge < edges.len() && edges[next_edge].y_top <= sample_y {
+                active.push(next_edge);
+                next_edge += 1;
+            }
+            active.retain(|index| edges[*index].y_bottom > sample_y);
+
+            crossings.clear();
+            for index in &active {
+                let edge = &edges[*index];
+                crossings.push((edge.x_at(sample_y), edge.winding));
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                let inside = match rule {
+                    FillRule::NonZero => winding != 0,
+                    FillRule::EvenOdd => winding % 2 != 0,
+                };
+                if !inside {
+                    continue;
+                }
+                let start = pair[0].0.max(0.0);
+                let end = pair[1].0.min(width as f32);
+                if start >= end {
+                    continue;
+                }
+
+                if anti_alias {
+                    let first = start.floor() as usize;
+                    let last = (end.ceil() as usize).min(width);
+                    for (column, value) in coverage.iter_mut().enumerate().take(last).skip(first) {
+                        let overlap = end.min(column as f32 + 1.0) - start.max(column as f32);
+                        *value += overlap.max(0.0) * sample_weight;
+                    }
+                    touched = (touched.0.min(first), touched.1.max(last));
+                } else {
+                    let first = (start - 0.5).ceil().max(0.0) as usize;
+                    let last = ((end - 0.5).ceil().max(0.0) as usize).min(width);
+                    for value in coverage.iter_mut().take(last).skip(first) {
+                        *value = 1.0;
+                    }
+                    touched = (touched.0.min(first), touched.1.max(last));
+                }
+            }
+        }
+
+        for (x, value) in coverage
+            .iter_mut()
+            .enumerate()
+            .take(touched.1)
+            .skip(touched.0)
+        {
+            if *value > 0.001 {
+                plot(x as isize, y as isize, value.min(1.0));
+            }
+            *value = 0.0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    /// Five pointed star drawn in one stroke, its center pentagon is wound twice.
+    fn star() -> Vec<(f32, f32)> {
+        (0..5)
+            .map(|index| {
+                let angle = -PI / 2.0 + index as f32 * 4.0 * PI / 5.0;
+                (50.0 + 40.0 * angle.cos(), 50.0 + 40.0 * angle.sin())
+            })
+            .collect()
+    }
+
+    fn coverage(rule: FillRule) -> Vec<f32> {
+        let mut covered = vec![0.0; 100 * 100];
+        fill_contours(&[star()], rule, 100, 100, false, |x, y, amount| {
+            covered[y as usize * 100 + x as usize] += amount;
+        });
+        covered
+    }
+
+    #[test]
+    fn star_center_depends_on_fill_rule() {
+        let non_zero = coverage(FillRule::NonZero);
+        let even_odd = coverage(FillRule::EvenOdd);
+        let center = 50 * 100 + 50;
+        assert_eq!(non_zero[center], 1.0);
+        assert_eq!(even_odd[center], 0.0);
+
+        // a point of the star is inside once, whatever the rule
+        let tip = 20 * 100 + 50;
+        assert_eq!(non_zero[tip], 1.0);
+        assert_eq!(even_odd[tip], 1.0);
+        assert_eq!(non_zero[5 * 100 + 5], 0.0);
+    }
+
+    #[test]
+    fn pixels_are_plotted_once() {
+        for rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            assert!(coverage(rule).iter().all(|amount| *amount <= 1.0));
+        }
+    }
+
+    #[test]
+    fn square_covers_pixels_between_its_edges() {
+        // the right and bottom edge are exclusive, like in scanline fills elsewhere
+        let square = vec![(2.0, 2.0), (5.0, 2.0), (5.0, 5.0), (2.0, 5.0)];
+        let mut plotted = Vec::new();
+        fill_contours(
+            &[square],
+            FillRule::NonZero,
+            10,
+            10,
+            false,
+            |x, y, amount| plotted.push((x, y, amount)),
+        );
+        plotted.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        let expected: Vec<(isize, isize, f32)> = (2..5)
+            .flat_map(|x| (2..5).map(move |y| (x, y, 1.0)))
+            .collect();
+        assert_eq!(plotted, expected);
+    }
+}
@@ -1,8 +1,18 @@
 use super::canvas::Canvas;
-use std::io::Write;
+use crate::error::Error;
+use crate::input::{InputEvent, Key, Modifiers, MouseButton};
+use crate::pdf::PdfDocument;
+use crate::quantize::Histogram;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 extern crate sdl2;
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::pixels::PixelMasks;
 use sdl2::render::{TextureCreator, WindowCanvas};
 use sdl2::surface::Surface;
@@ -12,10 +22,44 @@ use sdl2::EventPump;
 pub enum RendererType<'a> {
     PPM(Box<dyn Write>),
     SDL2(&'a str),
+    PNG(FrameSequence),
+    Video(VideoOptions),
+    GIF(GifOptions),
+    /// Records the canvas as vector shapes and writes every frame as SVG.
+    SVG(FrameSequence),
+    /// Records the canvas as vector shapes and writes one PDF page per frame.
+    PDF(PathBuf),
 }
 
 pub trait Renderer {
-    fn update(&mut self, canvas: &Canvas) -> bool;
+    /// Presents the frame, returns `Ok(false)` once the sketch should stop.
+    fn update(&mut self, canvas: &Canvas) -> Result<bool, Error>;
+
+    /// Input received since the last call, backends without a window report none.
+    fn poll_events(&mut self) -> Vec<InputEvent> {
+        Vec::new()
+    }
+
+    /// Whether a user is watching and interacting, a paused sketch keeps
+    /// running for interactive renderers and ends for all others.
+    fn is_interactive(&self) -> bool {
+        false
+    }
+
+    /// Whether the backend draws from the canvas' display list, `Sketch`
+    /// starts recording for such renderers.
+    fn needs_display_list(&self) -> bool {
+        false
+    }
+
+    /// Called once when the sketch ends to flush and close the output.
+    fn finish(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+fn backend_error(error: impl ToString) -> Error {
+    Error::Backend(error.to_string())
 }
 
 pub struct PPMRenderer {
@@ -29,85 +73,915 @@ impl PPMRenderer {
 }
 
 impl Renderer for PPMRenderer {
-    fn update(&mut self, canvas: &Canvas) -> bool {
+    fn update(&mut self, canvas: &Canvas) -> Result<bool, Error> {
         writeln!(
             self.writer,
             "P6\n{} {}\n255",
             canvas.width(),
             canvas.height()
-        )
-        .unwrap();
-        self.writer.write(canvas.as_raw_buffer()).unwrap();
+        )?;
+        let mut rgb = Vec::with_capacity(canvas.width() * canvas.height() * 3);
+        for pixel in canvas.as_raw_buffer().chunks_exact(4) {
+            rgb.extend_from_slice(&pixel[..3]);
+        }
+        self.writer.write_all(&rgb)?;
+        Ok(true)
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+pub struct FrameSequence {
+    directory: PathBuf,
+    pattern: String,
+    padding: usize,
+    range: Option<Range<usize>>,
+}
+
+impl FrameSequence {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        FrameSequence {
+            directory: directory.into(),
+            pattern: String::from("frame_{}.png"),
+            padding: 6,
+            range: None,
+        }
+    }
+
+    /// The first `{}` in the pattern is replaced by the zero padded frame
+    /// number. Renderers reject patterns without one unless the frame range
+    /// holds a single frame.
+    pub fn pattern(mut self, pattern: &str) -> Self {
+        self.pattern = pattern.to_string();
+        self
+    }
+
+    pub fn padding(mut self, padding: usize) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn frame_range(mut self, range: Range<usize>) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    pub fn directory(&self) -> &PathBuf {
+        &self.directory
+    }
+
+    pub fn file_name(&self, frame: usize) -> PathBuf {
+        let number = format!("{:0width$}", frame, width = self.padding);
+        self.directory.join(self.pattern.replacen("{}", &number, 1))
+    }
+
+    /// Makes sure every frame of the sequence gets a file of its own.
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let single_frame = self.range.as_ref().is_some_and(|range| range.len() <= 1);
+        if !self.pattern.contains("{}") && !single_frame {
+            return Err(backend_error(format!(
+                "file name pattern \"{}\" has no {{}} for the frame number",
+                self.pattern
+            )));
+        }
+        Ok(())
+    }
+
+    /// Replaces the extension of the pattern, failing if the frame number
+    /// would be replaced along with it, e.g. in `frame.{}`.
+    pub(crate) fn with_extension(mut self, extension: &str) -> Result<Self, Error> {
+        let pattern = Path::new(&self.pattern).with_extension(extension);
+        let pattern = pattern.to_string_lossy();
+        if self.pattern.contains("{}") && !pattern.contains("{}") {
+            return Err(backend_error(format!(
+                "file name pattern \"{}\" has the frame number in its extension",
+                self.pattern
+            )));
+        }
+        self.pattern = pattern.into_owned();
+        Ok(self)
+    }
+
+    pub fn contains(&self, frame: usize) -> bool {
+        match self.range {
+            Some(ref range) => range.contains(&frame),
+            None => true,
+        }
+    }
+
+    pub fn is_finished(&self, frame: usize) -> bool {
+        match self.range {
+            Some(ref range) => frame >= range.end,
+            None => false,
+        }
+    }
+}
+
+/// Writes every frame as a separate PNG file, stops the sketch once the
+/// configured frame range has been captured.
+pub struct PNGRenderer {
+    sequence: FrameSequence,
+    frame: usize,
+}
+
+impl PNGRenderer {
+    pub fn new(sequence: FrameSequence) -> Self {
+        PNGRenderer::try_new(sequence).unwrap()
+    }
+
+    pub fn try_new(sequence: FrameSequence) -> Result<Self, Error> {
+        sequence.validate()?;
+        fs::create_dir_all(sequence.directory())?;
+        Ok(PNGRenderer { sequence, frame: 0 })
+    }
+}
+
+impl Renderer for PNGRenderer {
+    fn update(&mut self, canvas: &Canvas) -> Result<bool, Error> {
+        let frame = self.frame;
+        self.frame += 1;
+
+        if self.sequence.contains(frame) {
+            let file = File::create(self.sequence.file_name(frame))?;
+            let mut encoder = png::Encoder::new(
+                BufWriter::new(file),
+                canvas.width() as u32,
+                canvas.height() as u32,
+            );
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().map_err(backend_error)?;
+            writer
+                .write_image_data(canvas.as_raw_buffer())
+                .map_err(backend_error)?;
+        }
+
+        Ok(!self.sequence.is_finished(self.frame))
+    }
+}
+
+/// Writes the display list of every frame as a separate SVG file, the file
+/// names of the sequence get an `.svg` extension. The canvas has to record,
+/// which `Sketch` sets up for this renderer.
+pub struct SVGRenderer {
+    sequence: FrameSequence,
+    frame: usize,
+}
+
+impl SVGRenderer {
+    pub fn new(sequence: FrameSequence) -> Self {
+        SVGRenderer::try_new(sequence).unwrap()
+    }
+
+    pub fn try_new(sequence: FrameSequence) -> Result<Self, Error> {
+        let sequence = sequence.with_extension("svg")?;
+        sequence.validate()?;
+        fs::create_dir_all(sequence.directory())?;
+        Ok(SVGRenderer { sequence, frame: 0 })
+    }
+}
+
+impl Renderer for SVGRenderer {
+    fn update(&mut self, canvas: &Canvas) -> Result<bool, Error> {
+        let frame = self.frame;
+        self.frame += 1;
+
+        if self.sequence.contains(frame) {
+            let display_list = canvas
+                .display_list()
+                .ok_or_else(|| backend_error("the canvas does not record a display list"))?;
+            fs::write(self.sequence.file_name(frame), display_list.to_svg())?;
+        }
+
+        Ok(!self.sequence.is_finished(self.frame))
+    }
+
+    fn needs_display_list(&self) -> bool {
         true
     }
 }
 
+/// Writes every frame as a page of one PDF document, a pixel of the canvas
+/// becomes a point on the page. TrueType fonts are embedded as outlines, the
+/// bundled bitmap font and fonts with CFF outlines or from font collections
+/// as the bitmap glyphs the canvas renders at the text's size.
+pub struct PDFRenderer {
+    document: Option<PdfDocument<BufWriter<File>>>,
+}
+
+impl PDFRenderer {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        PDFRenderer::try_new(path).unwrap()
+    }
+
+    pub fn try_new(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let document = PdfDocument::new(BufWriter::new(File::create(path)?))?;
+        Ok(PDFRenderer {
+            document: Some(document),
+        })
+    }
+}
+
+impl Renderer for PDFRenderer {
+    fn update(&mut self, canvas: &Canvas) -> Result<bool, Error> {
+        let display_list = canvas
+            .display_list()
+            .ok_or_else(|| backend_error("the canvas does not record a display list"))?;
+        let document = self
+            .document
+            .as_mut()
+            .ok_or_else(|| backend_error("the PDF document is already finished"))?;
+        document.add_page(display_list)?;
+        Ok(true)
+    }
+
+    fn needs_display_list(&self) -> bool {
+        true
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        if let Some(document) = self.document.take() {
+            document.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Rate control of the encoder, a constant quality factor or a target bitrate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoQuality {
+    /// Constant rate factor, lower is better, 18 to 28 is sensible for H.264.
+    Crf(u32),
+    /// Target bitrate in kilobits per second.
+    Bitrate(u32),
+}
+
+/// Settings of a `VideoRenderer`. Without an explicit codec `.webm` files are
+/// encoded with VP9 and everything else with H.264.
+#[derive(Clone, Debug)]
+pub struct VideoOptions {
+    path: PathBuf,
+    codec: Option<String>,
+    frame_rate: f32,
+    quality: VideoQuality,
+    pixel_format: String,
+    ffmpeg: PathBuf,
+}
+
+impl VideoOptions {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        VideoOptions {
+            path: path.into(),
+            codec: None,
+            frame_rate: 30.0,
+            quality: VideoQuality::Crf(23),
+            pixel_format: String::from("yuv420p"),
+            ffmpeg: PathBuf::from("ffmpeg"),
+        }
+    }
+
+    /// Any encoder name ffmpeg knows, e.g. `libx264`, `libx265` or `libvpx-vp9`.
+    pub fn codec(mut self, codec: &str) -> Self {
+        self.codec = Some(codec.to_string());
+        self
+    }
+
+    /// Playback rate of the video, independent of how fast the sketch runs.
+    pub fn frame_rate(mut self, frame_rate: f32) -> Self {
+        self.frame_rate = frame_rate;
+        self
+    }
+
+    pub fn crf(mut self, crf: u32) -> Self {
+        self.quality = VideoQuality::Crf(crf);
+        self
+    }
+
+    pub fn bitrate(mut self, kilobits_per_second: u32) -> Self {
+        self.quality = VideoQuality::Bitrate(kilobits_per_second);
+        self
+    }
+
+    /// Pixel format of the encoded video, `yuv420p` plays almost everywhere
+    /// but needs an even canvas width and height.
+    pub fn pixel_format(mut self, pixel_format: &str) -> Self {
+        self.pixel_format = pixel_format.to_string();
+        self
+    }
+
+    /// The ffmpeg executable, looked up in `PATH` by default.
+    pub fn ffmpeg_path<P: Into<PathBuf>>(mut self, ffmpeg: P) -> Self {
+        self.ffmpeg = ffmpeg.into();
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn codec_name(&self) -> &str {
+        match self.codec {
+            Some(ref codec) => codec,
+            None => match self
+                .path
+                .extension()
+                .and_then(|extension| extension.to_str())
+            {
+                Some(extension) if extension.eq_ignore_ascii_case("webm") => "libvpx-vp9",
+                _ => "libx264",
+            },
+        }
+    }
+
+    fn ffmpeg_command(&self, width: usize, height: usize) -> Command {
+        let mut command = Command::new(&self.ffmpeg);
+        command
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .arg("-s")
+            .arg(format!("{}x{}", width, height))
+            .arg("-r")
+            .arg(self.frame_rate.to_string())
+            .args(["-i", "-", "-c:v", self.codec_name()]);
+        match self.quality {
+            VideoQuality::Crf(crf) => command.arg("-crf").arg(crf.to_string()),
+            VideoQuality::Bitrate(bitrate) => command.arg("-b:v").arg(format!("{}k", bitrate)),
+        };
+        // VP9 only honours the crf in constant quality mode
+        if matches!(self.quality, VideoQuality::Crf(_)) && self.codec_name() == "libvpx-vp9" {
+            command.args(["-b:v", "0"]);
+        }
+        command
+            .arg("-pix_fmt")
+            .arg(&self.pixel_format)
+            .arg(&self.path);
+        command
+    }
+}
+
+/// Encodes the frames into a video file by piping them into an ffmpeg
+/// process. The file is complete once the sketch has finished running.
+pub struct VideoRenderer {
+    encoder: Option<Child>,
+    input: Option<ChildStdin>,
+    diagnostics: Option<JoinHandle<String>>,
+}
+
+impl VideoRenderer {
+    pub fn new(options: VideoOptions, width: usize, height: usize) -> Self {
+        VideoRenderer::try_new(options, width, height).unwrap()
+    }
+
+    pub fn try_new(options: VideoOptions, width: usize, height: usize) -> Result<Self, Error> {
+        let mut encoder = options
+            .ffmpeg_command(width, height)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| {
+                Error::Backend(format!(
+                    "could not start {}: {}",
+                    options.ffmpeg.display(),
+                    error
+                ))
+            })?;
+        let input = encoder.stdin.take();
+        // drained on a separate thread so a chatty encoder never blocks on a full pipe
+        let diagnostics = encoder.stderr.take().map(|mut stderr| {
+            thread::spawn(move || {
+                let mut output = String::new();
+                let _ = stderr.read_to_string(&mut output);
+                output
+            })
+        });
+        Ok(VideoRenderer {
+            encoder: Some(encoder),
+            input,
+            diagnostics,
+        })
+    }
+}
+
+impl Renderer for VideoRenderer {
+    fn update(&mut self, canvas: &Canvas) -> Result<bool, Error> {
+        let input = match self.input {
+            Some(ref mut input) => input,
+            None => return Err(backend_error("the video encoder was already closed")),
+        };
+        match input.write_all(canvas.as_raw_buffer()) {
+            Ok(()) => Ok(true),
+            // the encoder quit, its exit status and messages explain why
+            Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {
+                self.finish()?;
+                Err(backend_error("the video encoder stopped accepting frames"))
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        // closing the pipe tells ffmpeg the stream ended
+        drop(self.input.take());
+        let mut encoder = match self.encoder.take() {
+            Some(encoder) => encoder,
+            None => return Ok(()),
+        };
+        let status = encoder.wait()?;
+        let diagnostics = self
+            .diagnostics
+            .take()
+            .and_then(|diagnostics| diagnostics.join().ok())
+            .unwrap_or_default();
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::Backend(format!(
+                "video encoder failed ({}): {}",
+                status,
+                diagnostics.trim()
+            )))
+        }
+    }
+}
+
+impl Drop for VideoRenderer {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+/// Where the colors of a GIF frame come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GifPalette {
+    /// One palette built from all frames, which avoids flickering colors but
+    /// keeps every frame in memory until the sketch ends.
+    Global,
+    /// Each frame is quantized and written on its own.
+    PerFrame,
+}
+
+/// Settings of a `GifRenderer`, by default frames are shown for 40ms each,
+/// get their own palette and the animation loops forever.
+#[derive(Clone, Debug)]
+pub struct GifOptions {
+    path: PathBuf,
+    delay: Duration,
+    loop_count: Option<u16>,
+    palette: GifPalette,
+    colors: usize,
+    dithering: bool,
+    max_frames: Option<usize>,
+}
+
+impl GifOptions {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        GifOptions {
+            path: path.into(),
+            delay: Duration::from_millis(40),
+            loop_count: None,
+            palette: GifPalette::PerFrame,
+            colors: 256,
+            dithering: false,
+            max_frames: None,
+        }
+    }
+
+    /// How long each frame is shown, GIFs store it in hundredths of a second.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Number of times the animation repeats after playing once.
+    pub fn loop_count(mut self, loop_count: u16) -> Self {
+        self.loop_count = Some(loop_count);
+        self
+    }
+
+    pub fn palette(mut self, palette: GifPalette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Size of the palette, between 2 and 256 colors.
+    pub fn colors(mut self, colors: usize) -> Self {
+        self.colors = colors.clamp(2, 256);
+        self
+    }
+
+    /// Hides banding in gradients with Floyd–Steinberg dithering.
+    pub fn dithering(mut self, dithering: bool) -> Self {
+        self.dithering = dithering;
+        self
+    }
+
+    /// Stops the sketch once this many frames have been captured.
+    pub fn max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = Some(max_frames);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Writes an animated GIF. Colors are reduced to the palette with median cut
+/// quantization, transparency of the canvas is ignored.
+pub struct GifRenderer {
+    options: GifOptions,
+    width: u16,
+    height: u16,
+    writer: Option<BufWriter<File>>,
+    encoder: Option<gif::Encoder<BufWriter<File>>>,
+    histogram: Option<Histogram>,
+    pending_frames: Vec<Vec<u8>>,
+    frame: usize,
+}
+
+impl GifRenderer {
+    pub fn new(options: GifOptions, width: usize, height: usize) -> Self {
+        GifRenderer::try_new(options, width, height).unwrap()
+    }
+
+    pub fn try_new(options: GifOptions, width: usize, height: usize) -> Result<Self, Error> {
+        let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+            (Ok(width), Ok(height)) => (width, height),
+            _ => {
+                return Err(backend_error(
+                    "GIF frames are limited to 65535x65535 pixels",
+                ))
+            }
+        };
+        let writer = BufWriter::new(File::create(&options.path)?);
+        Ok(GifRenderer {
+            options,
+            width,
+            height,
+            writer: Some(writer),
+            encoder: None,
+            histogram: None,
+            pending_frames: Vec::new(),
+            frame: 0,
+        })
+    }
+
+    /// Creates the encoder on first use, the global palette has to be known
+    /// before the header can be written.
+    fn encoder(
+        &mut self,
+        global_palette: &[u8],
+    ) -> Result<&mut gif::Encoder<BufWriter<File>>, Error> {
+        if self.encoder.is_none() {
+            let writer = match self.writer.take() {
+                Some(writer) => writer,
+                None => return Err(backend_error("the GIF file was already closed")),
+            };
+            let mut encoder = gif::Encoder::new(writer, self.width, self.height, global_palette)
+                .map_err(backend_error)?;
+            let repeat = match self.options.loop_count {
+                Some(count) => gif::Repeat::Finite(count),
+                None => gif::Repeat::Infinite,
+            };
+            encoder.set_repeat(repeat).map_err(backend_error)?;
+            self.encoder = Some(encoder);
+        }
+        Ok(self.encoder.as_mut().unwrap())
+    }
+
+    fn write_frame(&mut self, indices: Vec<u8>, palette: Option<Vec<u8>>) -> Result<(), Error> {
+        let centiseconds = (self.options.delay.as_millis() as f32 / 10.0).round();
+        let frame = gif::Frame {
+            width: self.width,
+            height: self.height,
+            delay: centiseconds.min(u16::MAX as f32) as u16,
+            palette,
+            buffer: indices.into(),
+            ..gif::Frame::default()
+        };
+        self.encoder(&[])?
+            .write_frame(&frame)
+            .map_err(backend_error)
+    }
+}
+
+impl Renderer for GifRenderer {
+    fn update(&mut self, canvas: &Canvas) -> Result<bool, Error> {
+        let max_frames = self.options.max_frames;
+        let captured_all = |frame| max_frames.is_some_and(|max_frames| frame >= max_frames);
+        if captured_all(self.frame) {
+            return Ok(false);
+        }
+        let rgba = canvas.as_raw_buffer();
+        match self.options.palette {
+            GifPalette::Global => {
+                self.histogram.get_or_insert_with(Histogram::new).add(rgba);
+                self.pending_frames.push(rgba.to_vec());
+            }
+            GifPalette::PerFrame => {
+                let mut histogram = Histogram::new();
+                histogram.add(rgba);
+                let mut palette = histogram.palette(self.options.colors);
+                let indices = palette.index(rgba, canvas.width(), self.options.dithering);
+                self.write_frame(indices, Some(palette.to_rgb()))?;
+            }
+        }
+        self.frame += 1;
+        Ok(!captured_all(self.frame))
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        if let Some(histogram) = self.histogram.take() {
+            let mut palette = histogram.palette(self.options.colors);
+            self.encoder(&palette.to_rgb())?;
+            for rgba in std::mem::take(&mut self.pending_frames) {
+                let indices = palette.index(&rgba, self.width as usize, self.options.dithering);
+                self.write_frame(indices, None)?;
+            }
+        }
+        if self.writer.is_some() {
+            // no frame was captured, still leave a valid file behind
+            self.encoder(&[])?;
+        }
+        if let Some(encoder) = self.encoder.take() {
+            encoder.into_inner().map_err(backend_error)?.flush()?;
+        }
+        Ok(())
+    }
+}
+
 pub struct SDLRenderer {
     canvas: WindowCanvas,
     texture_creator: TextureCreator<WindowContext>,
     event_pump: EventPump,
+    quit_requested: bool,
+}
+
+fn sdl_key(keycode: Keycode) -> Key {
+    let code = keycode as i32;
+    match keycode {
+        Keycode::Return | Keycode::KpEnter => Key::Enter,
+        Keycode::Escape => Key::Escape,
+        Keycode::Backspace => Key::Backspace,
+        Keycode::Tab => Key::Tab,
+        Keycode::Delete => Key::Delete,
+        Keycode::Insert => Key::Insert,
+        Keycode::Home => Key::Home,
+        Keycode::End => Key::End,
+        Keycode::PageUp => Key::PageUp,
+        Keycode::PageDown => Key::PageDown,
+        Keycode::Left => Key::Left,
+        Keycode::Right => Key::Right,
+        Keycode::Up => Key::Up,
+        Keycode::Down => Key::Down,
+        Keycode::LShift | Keycode::RShift => Key::Shift,
+        Keycode::LCtrl | Keycode::RCtrl => Key::Control,
+        Keycode::LAlt | Keycode::RAlt => Key::Alt,
+        Keycode::LGui | Keycode::RGui => Key::Meta,
+        _ if (Keycode::F1 as i32..=Keycode::F12 as i32).contains(&code) => {
+            Key::Function((code - Keycode::F1 as i32 + 1) as u8)
+        }
+        // printable keys use their character as key code
+        _ => match char::from_u32(code as u32) {
+            Some(char) if !char.is_control() => Key::Char(char),
+            _ => Key::Other(code),
+        },
+    }
+}
+
+fn sdl_modifiers(keymod: Mod) -> Modifiers {
+    Modifiers {
+        shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+        control: keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD),
+        alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+        meta: keymod.intersects(Mod::LGUIMOD | Mod::RGUIMOD),
+    }
+}
+
+fn sdl_mouse_button(button: sdl2::mouse::MouseButton) -> MouseButton {
+    match button {
+        sdl2::mouse::MouseButton::Left => MouseButton::Left,
+        sdl2::mouse::MouseButton::Middle => MouseButton::Middle,
+        sdl2::mouse::MouseButton::Right => MouseButton::Right,
+        other => MouseButton::Other(other as u8),
+    }
 }
 
 impl SDLRenderer {
     pub fn new(title: &str, width: usize, height: usize) -> Self {
-        let sdl_context = sdl2::init().unwrap();
-        let video_subsystem = sdl_context.video().unwrap();
+        SDLRenderer::try_new(title, width, height).unwrap()
+    }
+
+    pub fn try_new(title: &str, width: usize, height: usize) -> Result<Self, Error> {
+        let sdl_context = sdl2::init().map_err(backend_error)?;
+        let video_subsystem = sdl_context.video().map_err(backend_error)?;
         let window = video_subsystem
             .window(title, width as u32, height as u32)
             .position_centered()
             .build()
-            .unwrap();
+            .map_err(backend_error)?;
 
-        let canvas = window.into_canvas().accelerated().build().unwrap();
+        let canvas = window
+            .into_canvas()
+            .accelerated()
+            .build()
+            .map_err(backend_error)?;
         let texture_creator = canvas.texture_creator();
-        let event_pump = sdl_context.event_pump().unwrap();
+        let event_pump = sdl_context.event_pump().map_err(backend_error)?;
 
-        SDLRenderer {
+        Ok(SDLRenderer {
             canvas,
             texture_creator,
             event_pump,
-        }
+            quit_requested: false,
+        })
     }
 }
 
 impl Renderer for SDLRenderer {
-    fn update(&mut self, canvas: &Canvas) -> bool {
+    fn is_interactive(&self) -> bool {
+        true
+    }
+
+    fn poll_events(&mut self) -> Vec<InputEvent> {
+        let mut events = Vec::new();
         for event in self.event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
+            let input_event = match event {
+                Event::Quit { .. } => {
+                    self.quit_requested = true;
+                    continue;
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    keymod,
+                    repeat,
                     ..
                 } => {
-                    return false;
+                    if keycode == Keycode::Escape {
+                        self.quit_requested = true;
+                    }
+                    InputEvent::KeyDown {
+                        key: sdl_key(keycode),
+                        modifiers: sdl_modifiers(keymod),
+                        repeat,
+                    }
                 }
-                _ => {}
-            }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    keymod,
+                    ..
+                } => InputEvent::KeyUp {
+                    key: sdl_key(keycode),
+                    modifiers: sdl_modifiers(keymod),
+                },
+                Event::MouseMotion { x, y, .. } => InputEvent::MouseMove {
+                    x: x as f32,
+                    y: y as f32,
+                },
+                Event::MouseButtonDown {
+                    mouse_btn, x, y, ..
+                } => InputEvent::MouseDown {
+                    button: sdl_mouse_button(mouse_btn),
+                    x: x as f32,
+                    y: y as f32,
+                },
+                Event::MouseButtonUp {
+                    mouse_btn, x, y, ..
+                } => InputEvent::MouseUp {
+                    button: sdl_mouse_button(mouse_btn),
+                    x: x as f32,
+                    y: y as f32,
+                },
+                Event::MouseWheel { x, y, .. } => InputEvent::MouseWheel {
+                    x: x as f32,
+                    y: y as f32,
+                },
+                Event::Window { win_event, .. } => match win_event {
+                    WindowEvent::SizeChanged(width, height) => InputEvent::Resized {
+                        width: width.max(0) as usize,
+                        height: height.max(0) as usize,
+                    },
+                    WindowEvent::FocusGained => InputEvent::FocusGained,
+                    WindowEvent::FocusLost => InputEvent::FocusLost,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            events.push(input_event);
+        }
+        events
+    }
+
+    fn update(&mut self, canvas: &Canvas) -> Result<bool, Error> {
+        if self.quit_requested {
+            return Ok(false);
         }
 
         let surface = Surface::from_data_pixelmasks(
             canvas.as_raw_buffer_mut(),
             canvas.width() as u32,
             canvas.height() as u32,
-            3 * canvas.width() as u32,
+            4 * canvas.width() as u32,
             PixelMasks {
-                bpp: 24,
-                rmask: 0x0000ff,
-                gmask: 0x00ff00,
-                bmask: 0xff0000,
+                bpp: 32,
+                rmask: 0x000000ff,
+                gmask: 0x0000ff00,
+                bmask: 0x00ff0000,
                 amask: 0,
             },
         )
-        .unwrap();
+        .map_err(backend_error)?;
         let texture = self
             .texture_creator
             .create_texture_from_surface(&surface)
-            .unwrap();
+            .map_err(backend_error)?;
         let rect = sdl2::rect::Rect::new(0, 0, canvas.width() as u32, canvas.height() as u32);
         self.canvas
             .copy_ex(&texture, Some(rect), Some(rect), 0.0, None, false, false)
-            .unwrap();
+            .map_err(backend_error)?;
         self.canvas.present();
-        true
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_sequence_file_names() {
+        let sequence = FrameSequence::new("out").pattern("f{}.png").padding(3);
+        assert_eq!(sequence.file_name(7), PathBuf::from("out/f007.png"));
+        assert!(sequence.validate().is_ok());
+    }
+
+    #[test]
+    fn frame_sequence_needs_frame_number() {
+        let still = || FrameSequence::new("out").pattern("still.png");
+        assert!(still().validate().is_err());
+        assert!(PNGRenderer::try_new(still()).is_err());
+        assert!(still().frame_range(3..4).validate().is_ok());
+    }
+
+    #[test]
+    fn svg_frames_swap_the_extension_of_the_pattern() {
+        let svg = |pattern| FrameSequence::new("out").pattern(pattern).padding(2);
+        let file_name = |pattern| svg(pattern).with_extension("svg").unwrap().file_name(3);
+        assert_eq!(file_name("f{}.png"), PathBuf::from("out/f03.svg"));
+        assert_eq!(file_name("f.{}.png"), PathBuf::from("out/f.03.svg"));
+        assert_eq!(file_name("f{}"), PathBuf::from("out/f03.svg"));
+        assert!(svg("frame.{}").with_extension("svg").is_err());
+        assert!(SVGRenderer::try_new(svg("frame.{}")).is_err());
+    }
+
+    #[test]
+    fn missing_ffmpeg_is_reported() {
+        let options = VideoOptions::new("out.mp4").ffmpeg_path("/nonexistent/ffmpeg");
+        match VideoRenderer::try_new(options, 4, 4) {
+            Err(Error::Backend(message)) => {
+                assert!(message.starts_with("could not start /nonexistent/ffmpeg"))
+            }
+            Err(error) => panic!("unexpected error {:?}", error),
+            Ok(_) => panic!("started a missing ffmpeg"),
+        }
+    }
+
+    /// Number of frames in the GIF written by `frames` updates.
+    fn gif_frames(options: GifOptions, frames: usize) -> usize {
+        let path = options.path().to_path_buf();
+        let mut renderer = GifRenderer::try_new(options, 4, 4).unwrap();
+        let canvas = Canvas::new(4, 4);
+        for _ in 0..frames {
+            renderer.update(&canvas).unwrap();
+        }
+        renderer.finish().unwrap();
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&path).unwrap())
+            .unwrap();
+        let mut count = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            count += 1;
+        }
+        std::fs::remove_file(path).unwrap();
+        count
+    }
+
+    #[test]
+    fn gif_stops_at_max_frames() {
+        let options = |name: &str, max_frames| {
+            let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+            GifOptions::new(path).max_frames(max_frames)
+        };
+        assert_eq!(gif_frames(options("two.gif", 2), 5), 2);
+        assert_eq!(gif_frames(options("none.gif", 0), 3), 0);
+        let global = options("global.gif", 0).palette(GifPalette::Global);
+        assert_eq!(gif_frames(global, 3), 0);
     }
 }
@@ -1,25 +1,100 @@
-use crate::renderer::SDLRenderer;
-use crate::sketch::RendererType::PPM;
+use crate::canvas::Recording;
+use crate::renderer::{
+    GifRenderer, PDFRenderer, PNGRenderer, SDLRenderer, SVGRenderer, VideoRenderer,
+};
+use crate::sketch::RendererType::{Video, GIF, PDF, PNG, PPM, SVG};
 
 use super::{
     canvas::Canvas,
+    error::Error,
+    input::{InputEvent, InputState, Key, MouseButton},
+    noise::Noise,
+    random::Random,
     renderer::{PPMRenderer, Renderer, RendererType, RendererType::SDL2},
 };
+use std::cell::Cell;
+use std::thread;
 use std::time::{Duration, Instant};
 
-pub type StateFn<State> = fn() -> State;
+/// Weight of the newest frame in the smoothed frames per second.
+const FPS_SMOOTHING: f32 = 0.1;
+
+/// Fixed updates run per frame at most, so a slow frame cannot make the
+/// loop fall further and further behind.
+const MAX_UPDATES_PER_FRAME: usize = 8;
+
+/// How often interactive renderers are polled while the loop is paused.
+const PAUSED_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub type SetupSketchFn<State> = Box<dyn FnOnce(&mut Sketch<State>)>;
 
-pub type SetupSketchFn<State> = fn(&mut Sketch<State>);
+pub type UpdateSketchFn<State> = Box<dyn FnMut(&mut State, &SketchMetrics)>;
 
-pub type UpdateSketchFn<State> = fn(&mut State, &SketchMetrics);
+pub type DrawSketchFn<State> = Box<dyn FnMut(DrawContext<'_, State>)>;
 
-pub type DrawSketchFn<State> = fn(&mut Canvas, &State, &SketchMetrics);
+pub type EventSketchFn<State> = Box<dyn FnMut(&mut State, &InputEvent, &SketchMetrics)>;
+
+pub type KeyPressedSketchFn<State> = Box<dyn FnMut(&mut State, Key, &SketchMetrics)>;
+
+pub type MousePressedSketchFn<State> = Box<dyn FnMut(&mut State, MouseButton, &SketchMetrics)>;
+
+/// Everything a frame is drawn from, handed to the draw callback.
+pub struct DrawContext<'a, State> {
+    pub canvas: &'a mut Canvas,
+    pub state: &'a State,
+    pub metrics: &'a SketchMetrics,
+    /// Shorthand for `metrics.input`.
+    pub input: &'a InputState,
+}
 
 #[derive(Default)]
 pub struct SketchMetrics {
     pub frame_count: usize,
+    /// Time since the previous frame, or the tick length inside fixed updates.
     pub delta_time: Duration,
-    pub frames_per_second: usize,
+    /// Exponentially smoothed frame rate.
+    pub frames_per_second: f32,
+    /// Time since the sketch started running.
+    pub elapsed: Duration,
+    /// Noise generator seeded through `Sketch::noise_seed`.
+    pub noise: Noise,
+    /// Random numbers seeded through `Sketch::random_seed`.
+    pub random: Random,
+    /// Mouse and keyboard state, updated before each frame's update.
+    pub input: InputState,
+    loop_paused: Cell<bool>,
+    redraw_requested: Cell<bool>,
+    stop_requested: Cell<bool>,
+}
+
+impl SketchMetrics {
+    /// Stops calling update and draw after the current frame. Sketches on a
+    /// renderer without a window end instead, so still images render once.
+    pub fn no_loop(&self) {
+        self.loop_paused.set(true);
+    }
+
+    pub fn resume_loop(&self) {
+        self.loop_paused.set(false);
+    }
+
+    pub fn is_looping(&self) -> bool {
+        !self.loop_paused.get()
+    }
+
+    /// Runs update and draw once more while the loop is paused.
+    pub fn redraw(&self) {
+        self.redraw_requested.set(true);
+    }
+
+    /// Ends the sketch once the current frame has been rendered.
+    pub fn stop(&self) {
+        self.stop_requested.set(true);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop_requested.get()
+    }
 }
 
 pub struct Sketch<State> {
@@ -29,48 +104,155 @@ pub struct Sketch<State> {
     on_setup: Option<SetupSketchFn<State>>,
     on_update: Option<UpdateSketchFn<State>>,
     on_draw: Option<DrawSketchFn<State>>,
+    on_event: Option<EventSketchFn<State>>,
+    on_key_pressed: Option<KeyPressedSketchFn<State>>,
+    on_mouse_pressed: Option<MousePressedSketchFn<State>>,
     renderer: Option<Box<dyn Renderer>>,
+    frame_duration: Option<Duration>,
+    update_tick: Option<Duration>,
+    max_frames: Option<usize>,
 }
 
 impl<State> Sketch<State> {
-    pub fn new(state_fn: StateFn<State>, width: usize, height: usize) -> Self {
-        let s = Sketch {
+    pub fn new(state_fn: impl FnOnce() -> State, width: usize, height: usize) -> Self {
+        Sketch::from_state(state_fn(), width, height)
+    }
+
+    /// Creates a sketch around an already built state, e.g. one configured
+    /// from command line arguments.
+    pub fn from_state(state: State, width: usize, height: usize) -> Self {
+        Sketch {
             canvas: Canvas::new(width, height),
-            state: state_fn(),
+            state,
             metrics: SketchMetrics::default(),
             on_setup: None,
             on_update: None,
             on_draw: None,
+            on_event: None,
+            on_key_pressed: None,
+            on_mouse_pressed: None,
             renderer: None,
-        };
-        s
+            frame_duration: None,
+            update_tick: None,
+            max_frames: None,
+        }
     }
 
-    pub fn renderer(mut self, renderer_type: RendererType) -> Self {
+    /// Panics if the renderer cannot be created, see `try_renderer`.
+    pub fn renderer(self, renderer_type: RendererType) -> Self {
+        self.try_renderer(renderer_type).unwrap()
+    }
+
+    pub fn try_renderer(mut self, renderer_type: RendererType) -> Result<Self, Error> {
         let renderer: Box<dyn Renderer> = match renderer_type {
             PPM(file) => Box::new(PPMRenderer::new(file)),
-            SDL2(title) => Box::new(SDLRenderer::new(
-                &title,
+            SDL2(title) => Box::new(SDLRenderer::try_new(
+                title,
+                self.canvas.width(),
+                self.canvas.height(),
+            )?),
+            PNG(sequence) => Box::new(PNGRenderer::try_new(sequence)?),
+            Video(options) => Box::new(VideoRenderer::try_new(
+                options,
                 self.canvas.width(),
                 self.canvas.height(),
-            )),
+            )?),
+            GIF(options) => Box::new(GifRenderer::try_new(
+                options,
+                self.canvas.width(),
+                self.canvas.height(),
+            )?),
+            SVG(sequence) => Box::new(SVGRenderer::try_new(sequence)?),
+            PDF(path) => Box::new(PDFRenderer::try_new(path)?),
         };
+        if renderer.needs_display_list() && self.canvas.display_list().is_none() {
+            self.canvas.begin_recording(Recording::VectorOnly);
+        }
         self.renderer = Some(renderer);
+        Ok(self)
+    }
+
+    /// Seeds the noise available as `SketchMetrics::noise`, so noise driven
+    /// sketches render the same on every run.
+    pub fn noise_seed(mut self, seed: u64) -> Self {
+        self.metrics.noise = Noise::new(seed);
         self
     }
 
-    pub fn setup(mut self, setup_fn: SetupSketchFn<State>) -> Self {
-        self.on_setup = Some(setup_fn);
+    /// Seeds `SketchMetrics::random`. Without a seed one is picked at random
+    /// and can be read back with `Random::seed` to reproduce a run.
+    pub fn random_seed(self, seed: u64) -> Self {
+        self.metrics.random.reseed(seed);
         self
     }
 
-    pub fn update(mut self, update_fn: UpdateSketchFn<State>) -> Self {
-        self.on_update = Some(update_fn);
+    /// Limits the sketch to `target` frames per second by sleeping after
+    /// each frame, without a limit frames are drawn as fast as possible.
+    pub fn frame_rate(mut self, target: f32) -> Self {
+        self.frame_duration = if target > 0.0 {
+            Some(Duration::from_secs_f32(1.0 / target))
+        } else {
+            None
+        };
         self
     }
 
-    pub fn draw(mut self, draw_fn: DrawSketchFn<State>) -> Self {
-        self.on_draw = Some(draw_fn);
+    /// Runs the update callback `ticks_per_second` times per second of
+    /// elapsed time regardless of the frame rate, e.g. for stable physics.
+    pub fn fixed_update_rate(mut self, ticks_per_second: f32) -> Self {
+        self.update_tick = if ticks_per_second > 0.0 {
+            Some(Duration::from_secs_f32(1.0 / ticks_per_second))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Ends the sketch after `frames` frames have been drawn, with 0 only
+    /// setup runs and no frame is drawn or rendered.
+    pub fn max_frames(mut self, frames: usize) -> Self {
+        self.max_frames = Some(frames);
+        self
+    }
+
+    pub fn setup(mut self, setup_fn: impl FnOnce(&mut Sketch<State>) + 'static) -> Self {
+        self.on_setup = Some(Box::new(setup_fn));
+        self
+    }
+
+    pub fn update(mut self, update_fn: impl FnMut(&mut State, &SketchMetrics) + 'static) -> Self {
+        self.on_update = Some(Box::new(update_fn));
+        self
+    }
+
+    pub fn draw(mut self, draw_fn: impl FnMut(DrawContext<'_, State>) + 'static) -> Self {
+        self.on_draw = Some(Box::new(draw_fn));
+        self
+    }
+
+    /// Called for every input event before the frame's update.
+    pub fn on_event(
+        mut self,
+        event_fn: impl FnMut(&mut State, &InputEvent, &SketchMetrics) + 'static,
+    ) -> Self {
+        self.on_event = Some(Box::new(event_fn));
+        self
+    }
+
+    /// Called once when a key goes down, auto repeats are only seen by `on_event`.
+    pub fn on_key_pressed(
+        mut self,
+        key_pressed_fn: impl FnMut(&mut State, Key, &SketchMetrics) + 'static,
+    ) -> Self {
+        self.on_key_pressed = Some(Box::new(key_pressed_fn));
+        self
+    }
+
+    pub fn on_mouse_pressed(
+        mut self,
+        mouse_pressed_fn: impl FnMut(&mut State, MouseButton, &SketchMetrics) + 'static,
+    ) -> Self {
+        self.on_mouse_pressed = Some(Box::new(mouse_pressed_fn));
         self
     }
 
@@ -88,6 +270,11 @@ impl<State> Sketch<State> {
         &self.canvas
     }
 
+    /// Gives setup access to the loop control, e.g. `sketch.metrics().no_loop()`.
+    pub fn metrics(&self) -> &SketchMetrics {
+        &self.metrics
+    }
+
     pub fn frame_count(&self) -> usize {
         self.metrics.frame_count
     }
@@ -95,34 +282,274 @@ impl<State> Sketch<State> {
     pub fn delta_time(&self) -> Duration {
         self.metrics.delta_time
     }
-    pub fn run(mut self) {
-        if let Some(setup_sketch_fn) = self.on_setup {
+
+    pub fn frames_per_second(&self) -> f32 {
+        self.metrics.frames_per_second
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.metrics.elapsed
+    }
+
+    pub fn noise(&self) -> &Noise {
+        &self.metrics.noise
+    }
+
+    pub fn random(&self) -> &Random {
+        &self.metrics.random
+    }
+
+    fn handle_event(&mut self, event: &InputEvent) {
+        self.metrics.input.apply(event);
+
+        if let Some(ref mut event_fn) = self.on_event {
+            event_fn(&mut self.state, event, &self.metrics);
+        }
+        match *event {
+            InputEvent::KeyDown {
+                key, repeat: false, ..
+            } => {
+                if let Some(ref mut key_pressed_fn) = self.on_key_pressed {
+                    key_pressed_fn(&mut self.state, key, &self.metrics);
+                }
+            }
+            InputEvent::MouseDown { button, .. } => {
+                if let Some(ref mut mouse_pressed_fn) = self.on_mouse_pressed {
+                    mouse_pressed_fn(&mut self.state, button, &self.metrics);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Runs the sketch until the renderer, `stop` or `max_frames` ends it,
+    /// errors of the renderer end the loop and are returned.
+    pub fn run(mut self) -> Result<(), Error> {
+        if let Some(setup_sketch_fn) = self.on_setup.take() {
             setup_sketch_fn(&mut self);
         }
 
+        let result = self.run_loop();
+        // finish even after a failed frame so files and encoders get closed
+        let finished = match self.renderer {
+            Some(ref mut renderer) => renderer.finish(),
+            None => Ok(()),
+        };
+        result.and(finished)
+    }
+
+    fn run_loop(&mut self) -> Result<(), Error> {
+        if self.max_frames == Some(0) {
+            return Ok(());
+        }
+        let interactive = self
+            .renderer
+            .as_ref()
+            .is_some_and(|renderer| renderer.is_interactive());
         let timer = Instant::now();
+        let mut previous_start = None;
+        // a full tick is pending so the first frame already sees an update
+        let mut pending_update_time = self.update_tick.unwrap_or_default();
         loop {
+            self.metrics
+                .random
+                .reseed_for_frame(self.metrics.frame_count);
+
+            self.metrics.input.begin_frame();
+            let events = match self.renderer {
+                Some(ref mut renderer) => renderer.poll_events(),
+                None => Vec::new(),
+            };
+            for event in &events {
+                self.handle_event(event);
+            }
+
+            let draw_frame = self.metrics.frame_count == 0
+                || self.metrics.is_looping()
+                || self.metrics.redraw_requested.take();
+            if !draw_frame {
+                if self.metrics.is_stopped() {
+                    break;
+                }
+                // keep the window responsive until the loop resumes
+                if let Some(ref mut renderer) = self.renderer {
+                    if !renderer.update(&self.canvas)? {
+                        break;
+                    }
+                }
+                previous_start = None;
+                thread::sleep(PAUSED_POLL_INTERVAL);
+                continue;
+            }
+
             let start = timer.elapsed();
+            let frame_time = previous_start.map_or(Duration::ZERO, |previous| start - previous);
+            previous_start = Some(start);
+            self.metrics.delta_time = frame_time;
+            self.metrics.elapsed = start;
+            if !frame_time.is_zero() {
+                let instant_rate = 1.0 / frame_time.as_secs_f32();
+                self.metrics.frames_per_second = if self.metrics.frames_per_second == 0.0 {
+                    instant_rate
+                } else {
+                    self.metrics.frames_per_second
+                        + (instant_rate - self.metrics.frames_per_second) * FPS_SMOOTHING
+                };
+            }
 
-            if let Some(ref update_sketch_fn) = self.on_update {
-                update_sketch_fn(&mut self.state, &self.metrics);
+            if let Some(ref mut update_sketch_fn) = self.on_update {
+                match self.update_tick {
+                    Some(tick) => {
+                        pending_update_time += frame_time;
+                        self.metrics.delta_time = tick;
+                        let mut updates = 0;
+                        while pending_update_time >= tick && updates < MAX_UPDATES_PER_FRAME {
+                            update_sketch_fn(&mut self.state, &self.metrics);
+                            pending_update_time -= tick;
+                            updates += 1;
+                        }
+                        if updates == MAX_UPDATES_PER_FRAME {
+                            pending_update_time = Duration::ZERO;
+                        }
+                        self.metrics.delta_time = frame_time;
+                    }
+                    None => update_sketch_fn(&mut self.state, &self.metrics),
+                }
             }
 
-            if let Some(ref draw_sketch_fn) = self.on_draw {
-                draw_sketch_fn(&mut self.canvas, &self.state, &self.metrics);
+            if let Some(ref mut draw_sketch_fn) = self.on_draw {
+                draw_sketch_fn(DrawContext {
+                    canvas: &mut self.canvas,
+                    state: &self.state,
+                    metrics: &self.metrics,
+                    input: &self.metrics.input,
+                });
             }
 
             if let Some(ref mut renderer) = self.renderer {
-                if !renderer.update(&self.canvas) {
+                if !renderer.update(&self.canvas)? {
                     break;
                 }
             }
             self.metrics.frame_count = self.metrics.frame_count.wrapping_add(1);
-            self.metrics.delta_time = timer.elapsed() - start;
-            if self.metrics.delta_time.as_millis() != 0 {
-                self.metrics.frames_per_second =
-                    (1000 as u128 / self.metrics.delta_time.as_millis()) as usize;
+
+            let reached_max_frames = self
+                .max_frames
+                .is_some_and(|max_frames| self.metrics.frame_count >= max_frames);
+            let paused_for_good = !self.metrics.is_looping() && !interactive;
+            if self.metrics.is_stopped() || reached_max_frames || paused_for_good {
+                break;
+            }
+
+            if let Some(frame_duration) = self.frame_duration {
+                let next_frame = start + frame_duration;
+                let now = timer.elapsed();
+                if now < next_frame {
+                    thread::sleep(next_frame - now);
+                }
             }
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Counts presented frames and calls to `finish`.
+    struct CountingRenderer {
+        frames: Rc<Cell<usize>>,
+        finished: Rc<Cell<usize>>,
+    }
+
+    impl Renderer for CountingRenderer {
+        fn update(&mut self, _canvas: &Canvas) -> Result<bool, Error> {
+            self.frames.set(self.frames.get() + 1);
+            Ok(true)
+        }
+
+        fn finish(&mut self) -> Result<(), Error> {
+            self.finished.set(self.finished.get() + 1);
+            Ok(())
+        }
+    }
+
+    /// Runs a sketch limited to `max_frames`, returns drawn, rendered and
+    /// finished counts.
+    fn run_limited(max_frames: usize) -> (usize, usize, usize) {
+        let drawn = Rc::new(Cell::new(0));
+        let frames = Rc::new(Cell::new(0));
+        let finished = Rc::new(Cell::new(0));
+        let mut sketch = Sketch::from_state((), 4, 4).max_frames(max_frames);
+        sketch.renderer = Some(Box::new(CountingRenderer {
+            frames: frames.clone(),
+            finished: finished.clone(),
+        }));
+        let counter = drawn.clone();
+        sketch
+            .draw(move |_| counter.set(counter.get() + 1))
+            .run()
+            .unwrap();
+        (drawn.get(), frames.get(), finished.get())
+    }
+
+    #[test]
+    fn max_frames_limits_drawn_frames() {
+        assert_eq!(run_limited(3), (3, 3, 1));
+        assert_eq!(run_limited(1), (1, 1, 1));
+    }
+
+    #[test]
+    fn zero_max_frames_renders_nothing() {
+        assert_eq!(run_limited(0), (0, 0, 1));
+    }
+
+    #[test]
+    fn stop_ends_after_current_frame() {
+        let drawn = Rc::new(Cell::new(0));
+        let counter = drawn.clone();
+        Sketch::from_state((), 4, 4)
+            .draw(move |ctx| {
+                counter.set(counter.get() + 1);
+                if ctx.metrics.frame_count == 4 {
+                    ctx.metrics.stop();
+                }
+            })
+            .run()
+            .unwrap();
+        assert_eq!(drawn.get(), 5);
+    }
+
+    #[test]
+    fn fixed_updates_see_the_tick_and_are_capped_per_frame() {
+        let tick = Duration::from_secs_f32(1.0 / 1000.0);
+        let updates = Rc::new(RefCell::new(Vec::new()));
+        let frames = Rc::new(RefCell::new(Vec::new()));
+        let (update_log, frame_log) = (updates.clone(), frames.clone());
+        Sketch::from_state((), 4, 4)
+            .fixed_update_rate(1000.0)
+            .max_frames(3)
+            .update(move |_, metrics| update_log.borrow_mut().push(metrics.delta_time))
+            .draw(move |ctx| {
+                frame_log.borrow_mut().push(ctx.metrics.delta_time);
+                // far more than MAX_UPDATES_PER_FRAME ticks pass until the next frame
+                thread::sleep(Duration::from_millis(20));
+            })
+            .run()
+            .unwrap();
+
+        // one pending tick for the first frame, then the capped catch up
+        let updates = updates.borrow();
+        assert_eq!(updates.len(), 1 + 2 * MAX_UPDATES_PER_FRAME);
+        assert!(updates.iter().all(|delta| *delta == tick));
+        // drawing still sees the real frame time
+        let frames = frames.borrow();
+        assert_eq!(frames[0], Duration::ZERO);
+        assert!(frames[1..]
+            .iter()
+            .all(|delta| *delta >= Duration::from_millis(20)));
     }
 }
@@ -0,0 +1,328 @@
+use std::f32::consts::PI;
+
+pub(crate) const MITER_LIMIT: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct StrokeStyle {
+    pub weight: f32,
+    pub cap: LineCap,
+    pub join: LineJoin,
+}
+
+type Point = (f32, f32);
+
+fn add(a: Point, b: Point) -> Point {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a: Point, factor: f32) -> Point {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn cross(a: Point, b: Point) -> f32 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+fn direction(from: Point, to: Point) -> Point {
+    let delta = sub(to, from);
+    let length = (delta.0 * delta.0 + delta.1 * delta.1).sqrt();
+    scale(delta, 1.0 / length)
+}
+
+fn normal(direction: Point) -> Point {
+    (-direction.1, direction.0)
+}
+
+fn signed_area(contour: &[Point]) -> f32 {
+    let mut area = 0.0;
+    for (index, start) in contour.iter().enumerate() {
+        area += cross(*start, contour[(index + 1) % contour.len()]);
+    }
+    area / 2.0
+}
+
+/// Orients the contour clockwise on screen, so all stroke pieces share the
+/// same winding direction and overlapping pieces fill only once.
+fn oriented(mut contour: Vec<Point>) -> Vec<Point> {
+    if signed_area(&contour) < 0.0 {
+        contour.reverse();
+    }
+    contour
+}
+
+pub(crate) fn circle(center: Point, radius: f32) -> Vec<Point> {
+    ellipse(center, radius, radius)
+}
+
+pub(crate) fn ellipse(center: Point, radius_x: f32, radius_y: f32) -> Vec<Point> {
+    let circumference = 2.0 * PI * radius_x.max(radius_y);
+    let steps = (circumference / 2.0).ceil().clamp(8.0, 256.0) as usize;
+    (0..steps)
+        .map(|step| {
+            let angle = 2.0 * PI * step as f32 / steps as f32;
+            (
+                center.0 + radius_x * angle.cos(),
+                center.1 + radius_y * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+/// Outline of an ellipse stroked with `weight`, the inner contour winds the
+/// other way so it cuts a hole into the outer one.
+pub(crate) fn ellipse_ring(
+    center: Point,
+    radius_x: f32,
+    radius_y: f32,
+    weight: f32,
+) -> Vec<Vec<Point>> {
+    let half = weight / 2.0;
+    let mut contours = vec![oriented(ellipse(center, radius_x + half, radius_y + half))];
+    if radius_x > half && radius_y > half {
+        let mut inner = oriented(ellipse(center, radius_x - half, radius_y - half));
+        inner.reverse();
+        contours.push(inner);
+    }
+    contours
+}
+
+fn join(vertex: Point, incoming: Point, outgoing: Point, style: &StrokeStyle) -> Vec<Vec<Point>> {
+    let half = style.weight / 2.0;
+    let turn = cross(incoming, outgoing);
+    if turn.abs() < 1e-6 && incoming.0 * outgoing.0 + incoming.1 * outgoing.1 > 0.0 {
+        return Vec::new();
+    }
+
+    if style.join == LineJoin::Round {
+        return vec![oriented(circle(vertex, half))];
+    }
+
+    // the outer side of the turn is the one the offset edges move away from
+    let side = if turn > 0.0 { -half } else { half };
+    let before = add(vertex, scale(normal(incoming), side));
+    let after = add(vertex, scale(normal(outgoing), side));
+
+    if style.join == LineJoin::Miter && turn.abs() >= 1e-6 {
+        let distance = cross(sub(after, before), outgoing) / turn;
+        let tip = add(before, scale(incoming, distance));
+        let miter = sub(tip, vertex);
+        if (miter.0 * miter.0 + miter.1 * miter.1).sqrt() <= MITER_LIMIT * half {
+            return vec![oriented(vec![vertex, before, tip, after])];
+        }
+    }
+    vec![oriented(vec![vertex, before, after])]
+}
+
+fn cap(end: Point, outward: Point, style: &StrokeStyle) -> Vec<Vec<Point>> {
+    let half = style.weight / 2.0;
+    match style.cap {
+        LineCap::Butt => Vec::new(),
+        LineCap::Round => vec![oriented(circle(end, half))],
+        LineCap::Square => {
+            let side = scale(normal(outward), half);
+            let extent = scale(outward, half);
+            vec![oriented(vec![
+                add(end, side),
+                add(add(end, side), extent),
+                add(sub(end, side), extent),
+                sub(end, side),
+            ])]
+        }
+    }
+}
+
+/// Converts a polyline into filled contours covering its stroke: one quad per
+/// segment plus the join and cap pieces, all oriented the same way.
+pub(crate) fn stroke_polyline(
+    points: &[Point],
+    closed: bool,
+    style: &StrokeStyle,
+) -> Vec<Vec<Point>> {
+    let mut points: Vec<Point> = points.to_vec();
+    points.dedup();
+    if closed && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    let half = style.weight / 2.0;
+    let mut contours = Vec::new();
+
+    if points.len() == 1 {
+        let dot = points[0];
+        match style.cap {
+            LineCap::Butt => {}
+            LineCap::Round => contours.push(oriented(circle(dot, half))),
+            LineCap::Square => contours.push(oriented(vec![
+                (dot.0 - half, dot.1 - half),
+                (dot.0 + half, dot.1 - half),
+                (dot.0 + half, dot.1 + half),
+                (dot.0 - half, dot.1 + half),
+            ])),
+        }
+        return contours;
+    }
+    if points.len() < 2 {
+        return contours;
+    }
+
+    let segment_count = if closed && points.len() > 2 {
+        points.len()
+    } else {
+        points.len() - 1
+    };
+    let closed = segment_count == points.len();
+    let directions: Vec<Point> = (0..segment_count)
+        .map(|index| direction(points[index], points[(index + 1) % points.len()]))
+        .collect();
+
+    for (index, dir) in directions.iter().enumerate() {
+        let start = points[index];
+        let end = points[(index + 1) % points.len()];
+        let offset = scale(normal(*dir), half);
+        contours.push(oriented(vec![
+            add(start, offset),
+            add(end, offset),
+            sub(end, offset),
+            sub(start, offset),
+        ]));
+    }
+
+    for index in 1..segment_count {
+        contours.extend(join(
+            points[index],
+            directions[index - 1],
+            directions[index],
+            style,
+        ));
+    }
+
+    if closed {
+        contours.extend(join(
+            points[0],
+            directions[segment_count - 1],
+            directions[0],
+            style,
+        ));
+    } else {
+        contours.extend(cap(points[0], scale(directions[0], -1.0), style));
+        contours.extend(cap(
+            points[segment_count],
+            directions[segment_count - 1],
+            style,
+        ));
+    }
+
+    contours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(weight: f32, cap: LineCap, join: LineJoin) -> StrokeStyle {
+        StrokeStyle { weight, cap, join }
+    }
+
+    /// Bounding box of all contours as `(left, top, right, bottom)`.
+    fn bounds(contours: &[Vec<Point>]) -> (f32, f32, f32, f32) {
+        let points = contours.iter().flatten();
+        points.fold(
+            (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+            |(left, top, right, bottom), (x, y)| {
+                (left.min(*x), top.min(*y), right.max(*x), bottom.max(*y))
+            },
+        )
+    }
+
+    fn assert_bounds(contours: &[Vec<Point>], expected: (f32, f32, f32, f32)) {
+        let actual = bounds(contours);
+        let actual = [actual.0, actual.1, actual.2, actual.3];
+        let expected_values = [expected.0, expected.1, expected.2, expected.3];
+        for (value, expected_value) in actual.into_iter().zip(expected_values) {
+            assert!(
+                (value - expected_value).abs() < 1e-4,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn caps_extend_open_lines() {
+        let line = [(0.0, 0.0), (10.0, 0.0)];
+        let stroke = |cap| stroke_polyline(&line, false, &style(4.0, cap, LineJoin::Miter));
+        assert_bounds(&stroke(LineCap::Butt), (0.0, -2.0, 10.0, 2.0));
+        assert_bounds(&stroke(LineCap::Square), (-2.0, -2.0, 12.0, 2.0));
+        assert_bounds(&stroke(LineCap::Round), (-2.0, -2.0, 12.0, 2.0));
+    }
+
+    #[test]
+    fn single_points_only_show_with_caps() {
+        let point = [(5.0, 5.0), (5.0, 5.0)];
+        let stroke = |cap| stroke_polyline(&point, false, &style(2.0, cap, LineJoin::Miter));
+        assert!(stroke(LineCap::Butt).is_empty());
+        assert_bounds(&stroke(LineCap::Square), (4.0, 4.0, 6.0, 6.0));
+        assert_bounds(&stroke(LineCap::Round), (4.0, 4.0, 6.0, 6.0));
+    }
+
+    #[test]
+    fn joins_shape_the_outer_corner() {
+        let corner = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)];
+        let stroke = |join| stroke_polyline(&corner, false, &style(2.0, LineCap::Butt, join));
+        assert_bounds(&stroke(LineJoin::Miter), (0.0, -1.0, 11.0, 10.0));
+        // the bevel cuts the corner, so no point reaches (11, -1)
+        let bevel = stroke(LineJoin::Bevel);
+        assert!(bevel
+            .iter()
+            .flatten()
+            .all(|(x, y)| !(*x > 10.5 && *y < -0.5)));
+        assert_bounds(&stroke(LineJoin::Round), (0.0, -1.0, 11.0, 10.0));
+    }
+
+    #[test]
+    fn sharp_miters_fall_back_to_bevels() {
+        let spike = [(0.0, 0.0), (10.0, 0.0), (0.0, 1.0)];
+        let miter = stroke_polyline(&spike, false, &style(2.0, LineCap::Butt, LineJoin::Miter));
+        let bevel = stroke_polyline(&spike, false, &style(2.0, LineCap::Butt, LineJoin::Bevel));
+        assert_eq!(miter, bevel);
+        assert!(bounds(&miter).2 < 10.0 + MITER_LIMIT);
+    }
+
+    #[test]
+    fn pieces_share_one_winding_direction() {
+        let polyline = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        for join in [LineJoin::Miter, LineJoin::Round, LineJoin::Bevel] {
+            let contours = stroke_polyline(&polyline, true, &style(3.0, LineCap::Round, join));
+            // a closed polyline gets four segments and four joins
+            assert_eq!(contours.len(), 8);
+            assert!(contours.iter().all(|contour| signed_area(contour) > 0.0));
+        }
+    }
+
+    #[test]
+    fn thin_ellipse_rings_have_a_hole() {
+        let ring = ellipse_ring((0.0, 0.0), 10.0, 5.0, 2.0);
+        assert_eq!(ring.len(), 2);
+        assert!(signed_area(&ring[0]) > 0.0 && signed_area(&ring[1]) < 0.0);
+        assert_eq!(ellipse_ring((0.0, 0.0), 10.0, 0.5, 2.0).len(), 1);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::primitives::FloatPoint;
+
+/// 2D affine transformation using the same layout as the SVG `matrix(a b c d e f)`:
+///
+/// x' = a * x + c * y + e
+/// y' = b * x + d * y + f
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    pub fn new(a: f32, b: f32, c: f32, d: f32, e: f32, f: f32) -> Self {
+        Transform { a, b, c, d, e, f }
+    }
+
+    pub fn identity() -> Self {
+        Transform::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+    }
+
+    pub fn translation(x: f32, y: f32) -> Self {
+        Transform::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    /// Rotation by `angle` radians, clockwise on screen.
+    pub fn rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Transform::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    pub fn scaling(x: f32, y: f32) -> Self {
+        Transform::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// Shear by the given angles in radians along the x and y axis.
+    pub fn shearing(angle_x: f32, angle_y: f32) -> Self {
+        Transform::new(1.0, angle_y.tan(), angle_x.tan(), 1.0, 0.0, 0.0)
+    }
+
+    /// Returns `self * other`, applying the result applies `other` first.
+    pub fn multiply(&self, other: &Transform) -> Transform {
+        Transform::new(
+            self.a * other.a + self.c * other.b,
+            self.b * other.a + self.d * other.b,
+            self.a * other.c + self.c * other.d,
+            self.b * other.c + self.d * other.d,
+            self.a * other.e + self.c * other.f + self.e,
+            self.b * other.e + self.d * other.f + self.f,
+        )
+    }
+
+    pub fn invert(&self) -> Option<Transform> {
+        let determinant = self.determinant();
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        Some(Transform::new(
+            self.d / determinant,
+            -self.b / determinant,
+            -self.c / determinant,
+            self.a / determinant,
+            (self.c * self.f - self.d * self.e) / determinant,
+            (self.b * self.e - self.a * self.f) / determinant,
+        ))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// Average factor lengths are scaled by, used to adapt stroke weights and
+    /// curve tolerances.
+    pub fn scale_factor(&self) -> f32 {
+        self.determinant().abs().sqrt()
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Transform::identity()
+    }
+
+    /// Returns the offset if the transform only translates.
+    pub fn as_translation(&self) -> Option<(f32, f32)> {
+        if self.a == 1.0 && self.b == 0.0 && self.c == 0.0 && self.d == 1.0 {
+            Some((self.e, self.f))
+        } else {
+            None
+        }
+    }
+
+    pub fn apply(&self, pt: &FloatPoint) -> FloatPoint {
+        FloatPoint::new(
+            self.a * pt.x + self.c * pt.y + self.e,
+            self.b * pt.x + self.d * pt.y + self.f,
+        )
+    }
+
+    pub(crate) fn apply_tuple(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(transform: Transform, expected: Transform) {
+        let values = |t: Transform| [t.a, t.b, t.c, t.d, t.e, t.f];
+        for (value, expected_value) in values(transform).into_iter().zip(values(expected)) {
+            assert!(
+                (value - expected_value).abs() < 1e-5,
+                "{:?} != {:?}",
+                transform,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn compose_applies_the_right_operand_first() {
+        let translate_then_scale =
+            Transform::scaling(2.0, 3.0).multiply(&Transform::translation(1.0, 1.0));
+        assert_eq!(
+            translate_then_scale.apply(&FloatPoint::new(1.0, 2.0)),
+            FloatPoint::new(4.0, 9.0)
+        );
+        let scale_then_translate =
+            Transform::translation(1.0, 1.0).multiply(&Transform::scaling(2.0, 3.0));
+        assert_eq!(
+            scale_then_translate.apply(&FloatPoint::new(1.0, 2.0)),
+            FloatPoint::new(3.0, 7.0)
+        );
+        assert_eq!(
+            Transform::identity().multiply(&translate_then_scale),
+            translate_then_scale
+        );
+    }
+
+    #[test]
+    fn rotation_is_clockwise_on_screen() {
+        let pt = Transform::rotation(std::f32::consts::FRAC_PI_2).apply(&FloatPoint::new(1.0, 0.0));
+        assert!(pt.x.abs() < 1e-6 && (pt.y - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn inverse_undoes_the_transform() {
+        let transform = Transform::translation(5.0, -3.0)
+            .multiply(&Transform::rotation(0.7))
+            .multiply(&Transform::scaling(2.0, 0.5))
+            .multiply(&Transform::shearing(0.3, 0.0));
+        let inverse = transform.invert().unwrap();
+        assert_close(transform.multiply(&inverse), Transform::identity());
+        assert_close(inverse.multiply(&transform), Transform::identity());
+        assert_close(inverse.invert().unwrap(), transform);
+    }
+
+    #[test]
+    fn singular_transforms_have_no_inverse() {
+        assert_eq!(Transform::scaling(0.0, 1.0).invert(), None);
+        assert_eq!(Transform::new(1.0, 2.0, 2.0, 4.0, 3.0, 3.0).invert(), None);
+    }
+}
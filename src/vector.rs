@@ -0,0 +1,322 @@
+use crate::primitives::FloatPoint;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec2 {
+    pub fn new(x: f32, y: f32) -> Self {
+        Vec2 { x, y }
+    }
+
+    /// Unit vector pointing in the direction of `angle` (radians).
+    pub fn from_angle(angle: f32) -> Self {
+        Vec2::new(angle.cos(), angle.sin())
+    }
+
+    pub fn dot(&self, other: &Vec2) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Z component of the cross product of both vectors lifted into 3D.
+    pub fn cross(&self, other: &Vec2) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn magnitude(&self) -> f32 {
+        self.magnitude_squared().sqrt()
+    }
+
+    pub fn magnitude_squared(&self) -> f32 {
+        self.dot(self)
+    }
+
+    /// Returns the vector scaled to length 1, the zero vector stays zero.
+    pub fn normalize(&self) -> Vec2 {
+        let magnitude = self.magnitude();
+        if magnitude == 0.0 {
+            *self
+        } else {
+            *self / magnitude
+        }
+    }
+
+    pub fn with_magnitude(&self, magnitude: f32) -> Vec2 {
+        self.normalize() * magnitude
+    }
+
+    pub fn limit(&self, max: f32) -> Vec2 {
+        if self.magnitude_squared() > max * max {
+            self.with_magnitude(max)
+        } else {
+            *self
+        }
+    }
+
+    pub fn distance(&self, other: &Vec2) -> f32 {
+        (*other - *self).magnitude()
+    }
+
+    /// Angle of the vector relative to the positive x axis in radians.
+    pub fn heading(&self) -> f32 {
+        self.y.atan2(self.x)
+    }
+
+    pub fn rotate(&self, angle: f32) -> Vec2 {
+        let (sin, cos) = angle.sin_cos();
+        Vec2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    /// Unsigned angle between both vectors in radians.
+    pub fn angle_between(&self, other: &Vec2) -> f32 {
+        let magnitudes = self.magnitude() * other.magnitude();
+        if magnitudes == 0.0 {
+            return 0.0;
+        }
+        (self.dot(other) / magnitudes).clamp(-1.0, 1.0).acos()
+    }
+
+    pub fn lerp_to(&self, to: &Vec2, amount: f32) -> Vec2 {
+        *self + (*to - *self) * amount
+    }
+}
+
+impl Vec3 {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Vec3 { x, y, z }
+    }
+
+    pub fn dot(&self, other: &Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn magnitude(&self) -> f32 {
+        self.magnitude_squared().sqrt()
+    }
+
+    pub fn magnitude_squared(&self) -> f32 {
+        self.dot(self)
+    }
+
+    /// Returns the vector scaled to length 1, the zero vector stays zero.
+    pub fn normalize(&self) -> Vec3 {
+        let magnitude = self.magnitude();
+        if magnitude == 0.0 {
+            *self
+        } else {
+            *self / magnitude
+        }
+    }
+
+    pub fn with_magnitude(&self, magnitude: f32) -> Vec3 {
+        self.normalize() * magnitude
+    }
+
+    pub fn limit(&self, max: f32) -> Vec3 {
+        if self.magnitude_squared() > max * max {
+            self.with_magnitude(max)
+        } else {
+            *self
+        }
+    }
+
+    pub fn distance(&self, other: &Vec3) -> f32 {
+        (*other - *self).magnitude()
+    }
+
+    /// Unsigned angle between both vectors in radians.
+    pub fn angle_between(&self, other: &Vec3) -> f32 {
+        let magnitudes = self.magnitude() * other.magnitude();
+        if magnitudes == 0.0 {
+            return 0.0;
+        }
+        (self.dot(other) / magnitudes).clamp(-1.0, 1.0).acos()
+    }
+
+    pub fn lerp_to(&self, to: &Vec3, amount: f32) -> Vec3 {
+        *self + (*to - *self) * amount
+    }
+
+    /// Drops the z component.
+    pub fn xy(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+}
+
+impl From<FloatPoint> for Vec2 {
+    fn from(pt: FloatPoint) -> Self {
+        Vec2::new(pt.x, pt.y)
+    }
+}
+
+macro_rules! impl_vector_ops {
+    ($vector:ident, $($component:ident),+) => {
+        impl Add for $vector {
+            type Output = $vector;
+            fn add(self, other: $vector) -> $vector {
+                $vector { $($component: self.$component + other.$component),+ }
+            }
+        }
+
+        impl Sub for $vector {
+            type Output = $vector;
+            fn sub(self, other: $vector) -> $vector {
+                $vector { $($component: self.$component - other.$component),+ }
+            }
+        }
+
+        impl Mul<f32> for $vector {
+            type Output = $vector;
+            fn mul(self, factor: f32) -> $vector {
+                $vector { $($component: self.$component * factor),+ }
+            }
+        }
+
+        impl Mul<$vector> for f32 {
+            type Output = $vector;
+            fn mul(self, vector: $vector) -> $vector {
+                vector * self
+            }
+        }
+
+        impl Div<f32> for $vector {
+            type Output = $vector;
+            fn div(self, divisor: f32) -> $vector {
+                $vector { $($component: self.$component / divisor),+ }
+            }
+        }
+
+        impl Neg for $vector {
+            type Output = $vector;
+            fn neg(self) -> $vector {
+                $vector { $($component: -self.$component),+ }
+            }
+        }
+
+        impl AddAssign for $vector {
+            fn add_assign(&mut self, other: $vector) {
+                *self = *self + other;
+            }
+        }
+
+        impl SubAssign for $vector {
+            fn sub_assign(&mut self, other: $vector) {
+                *self = *self - other;
+            }
+        }
+
+        impl MulAssign<f32> for $vector {
+            fn mul_assign(&mut self, factor: f32) {
+                *self = *self * factor;
+            }
+        }
+
+        impl DivAssign<f32> for $vector {
+            fn div_assign(&mut self, divisor: f32) {
+                *self = *self / divisor;
+            }
+        }
+    };
+}
+
+impl_vector_ops!(Vec2, x, y);
+impl_vector_ops!(Vec3, x, y, z);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::FloatRect;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(
+            (actual - expected).magnitude() < 1e-5,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn operators_work_component_wise() {
+        let mut vector = Vec2::new(1.0, 2.0) + Vec2::new(3.0, 4.0) - Vec2::new(0.5, 0.5);
+        assert_eq!(vector, Vec2::new(3.5, 5.5));
+        vector *= 2.0;
+        vector /= 4.0;
+        vector += Vec2::new(0.25, 0.25);
+        vector -= Vec2::new(1.0, 1.0);
+        assert_eq!(vector, Vec2::new(1.0, 2.0));
+        assert_eq!(2.0 * -vector, Vec2::new(-2.0, -4.0));
+        assert_eq!(Vec3::new(1.0, 2.0, 3.0) * 2.0, Vec3::new(2.0, 4.0, 6.0));
+    }
+
+    #[test]
+    fn magnitude_and_normalization() {
+        let vector = Vec2::new(3.0, 4.0);
+        assert_eq!(vector.magnitude(), 5.0);
+        assert_eq!(vector.normalize(), Vec2::new(0.6, 0.8));
+        assert_eq!(vector.with_magnitude(10.0), Vec2::new(6.0, 8.0));
+        assert_eq!(vector.limit(2.5), Vec2::new(1.5, 2.0));
+        assert_eq!(vector.limit(10.0), vector);
+        assert_eq!(Vec2::default().normalize(), Vec2::default());
+        assert_eq!(Vec3::new(0.0, 3.0, 4.0).distance(&Vec3::default()), 5.0);
+    }
+
+    #[test]
+    fn angles_and_rotation() {
+        assert_close(Vec2::from_angle(FRAC_PI_2), Vec2::new(0.0, 1.0));
+        assert_close(Vec2::new(1.0, 0.0).rotate(PI), Vec2::new(-1.0, 0.0));
+        assert!((Vec2::new(-1.0, 1.0).heading() - 3.0 * PI / 4.0).abs() < 1e-6);
+        let between = Vec2::new(1.0, 0.0).angle_between(&Vec2::new(0.0, -2.0));
+        assert!((between - FRAC_PI_2).abs() < 1e-6);
+        assert_eq!(Vec2::default().angle_between(&Vec2::new(1.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn products() {
+        assert_eq!(Vec2::new(1.0, 2.0).dot(&Vec2::new(3.0, 4.0)), 11.0);
+        assert_eq!(Vec2::new(1.0, 0.0).cross(&Vec2::new(0.0, 1.0)), 1.0);
+        assert_eq!(
+            Vec3::new(1.0, 0.0, 0.0).cross(&Vec3::new(0.0, 1.0, 0.0)),
+            Vec3::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            Vec2::new(0.0, 10.0).lerp_to(&Vec2::new(10.0, 0.0), 0.25),
+            Vec2::new(2.5, 7.5)
+        );
+    }
+
+    #[test]
+    fn rects_normalize_negative_sizes() {
+        let rect = FloatRect::new(FloatPoint::new(10.0, 10.0), -4.0, -6.0);
+        assert_eq!(
+            (rect.x(), rect.y(), rect.width, rect.height),
+            (6.0, 4.0, 4.0, 6.0)
+        );
+        assert!(rect.contains(&FloatPoint::new(10.0, 10.0)));
+        assert!(!rect.contains(&FloatPoint::new(10.5, 10.0)));
+        assert_eq!(rect.center(), FloatPoint::new(8.0, 7.0));
+        assert_eq!(
+            FloatPoint::from(Vec2::new(1.0, 2.0)),
+            FloatPoint::new(1.0, 2.0)
+        );
+    }
+}